@@ -0,0 +1,110 @@
+use crate::{cookies, logging, metrics, notify};
+use reqwest::header::{HeaderMap, COOKIE};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use std::time::Instant;
+
+pub const API_BASE: &str = "https://interface.carv.io/banana/";
+
+pub fn base_url() -> reqwest::Url {
+    reqwest::Url::parse(API_BASE).expect("API_BASE is a url")
+}
+
+/// A client sending every request through `proxy` when set.
+pub fn client(proxy: Option<&str>) -> reqwest::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    builder.build()
+}
+
+/// A fully read API response.
+pub struct ApiResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: String,
+}
+
+impl ApiResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_str(&self.body)
+    }
+
+    /// Seconds to wait before trying again, from the `Retry-After` header of a 429.
+    pub fn retry_after(&self) -> Option<u64> {
+        self.headers.get("Retry-After")?.to_str().ok()?.parse().ok()
+    }
+
+    /// The `code` field of the response body, if any.
+    pub fn code(&self) -> Option<i64> {
+        self.json::<serde_json::Value>()
+            .ok()
+            .and_then(|v| v["code"].as_i64())
+    }
+}
+
+/// Send `request` on behalf of `account` with the cookies of its jar, keep the ones the
+/// response sets and emit an [`logging::ApiEvent`] for it.
+pub async fn send(
+    account: &str,
+    action: &str,
+    request: RequestBuilder,
+) -> Result<ApiResponse, reqwest::Error> {
+    let (client, request) = request.build_split();
+    let mut request = request?;
+    if !request.headers().contains_key(COOKIE) {
+        if let Some(cookie) = cookies::header(account, request.url()) {
+            request.headers_mut().insert(COOKIE, cookie);
+        }
+    }
+    let url = request.url().clone();
+    let endpoint = request
+        .url()
+        .path_segments()
+        .and_then(|mut s| s.next_back())
+        .unwrap_or_default()
+        .to_string();
+
+    let start = Instant::now();
+    let result = async {
+        let response = client.execute(request).await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        cookies::store(account, &url, &headers);
+        let body = response.text().await?;
+        Ok::<_, reqwest::Error>(ApiResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+    .await;
+
+    let (status, code) = match &result {
+        Ok(response) => (Some(response.status().as_u16()), response.code()),
+        Err(err) => (err.status().map(|s| s.as_u16()), None),
+    };
+    let ok = status == Some(200) && code.unwrap_or(0) == 0;
+    metrics::on_request(account, &endpoint, status, code);
+    notify::on_request(account, ok);
+    logging::api_event(&logging::ApiEvent {
+        account,
+        action,
+        endpoint: &endpoint,
+        status,
+        code,
+        duration_ms: start.elapsed().as_millis(),
+        ok,
+    });
+
+    result
+}
@@ -0,0 +1,358 @@
+use crate::config::{self, ConfigError};
+use crate::goal::{Goal, Progress};
+use crate::init_data;
+use crate::logging::LogFormat;
+use crate::token;
+use crate::utils::User;
+use crate::{connect, next_claim_in, Banana};
+use clap::{Parser, Subcommand};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+#[command(version, about = "Banana Bot 🍌, free your hands now!")]
+pub struct Cli {
+    /// accounts file, or a directory of `{alias}.json` files
+    #[arg(
+        long,
+        global = true,
+        env = "BANANA_CONFIG",
+        default_value = "user.json"
+    )]
+    pub config: PathBuf,
+    /// `human` or `json`, overrides BANANA_LOG_FORMAT
+    #[arg(long, global = true)]
+    pub log_format: Option<LogFormat>,
+    /// only use these accounts (comma separated aliases)
+    #[arg(short, long = "account", global = true, value_delimiter = ',')]
+    pub accounts: Vec<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// click, claim and harvest until stopped (default)
+    Run,
+    /// one pass over every account for cron, exits with 1 if one failed
+    Once,
+    /// log in again and save the new tokens
+    Login { alias: String },
+    /// print the balance and counters of every account
+    Status,
+    /// manage the accounts file
+    #[command(subcommand)]
+    Accounts(AccountsCommand),
+    /// list the bananas of every account
+    Inventory,
+    /// list the quests of every account
+    Quests,
+    /// balance growth from the recorded history
+    #[command(alias = "report")]
+    Stats,
+    /// check the links, tokens and keys of the config
+    Validate,
+    /// move links and tokens from the config to the BANANA_SECRETS_FILE store
+    Encrypt,
+}
+
+#[derive(Subcommand)]
+pub enum AccountsCommand {
+    /// add an account from its Telegram web app link
+    Add {
+        alias: String,
+        /// the web app url, its `tgWebAppData` value or the decoded initData
+        link: String,
+        /// a referral code or a t.me link with `startapp`, defaults to the one of the link
+        #[arg(long)]
+        invite_code: Option<String>,
+    },
+    Remove {
+        alias: String,
+    },
+    List,
+}
+
+impl Cli {
+    /// Keep only the accounts selected with `--account`.
+    pub fn filter(&self, mut users: HashMap<String, User>) -> HashMap<String, User> {
+        if !self.accounts.is_empty() {
+            users.retain(|name, _| self.accounts.contains(name));
+        }
+        users
+    }
+
+    /// The accounts selected with `--account` that are not in `users`.
+    pub fn unknown<'a>(&'a self, users: &HashMap<String, User>) -> Vec<&'a str> {
+        self.accounts
+            .iter()
+            .filter(|name| !users.contains_key(*name))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+fn sorted(users: HashMap<String, User>) -> Vec<(String, User)> {
+    let mut users: Vec<_> = users.into_iter().collect();
+    users.sort_by(|a, b| a.0.cmp(&b.0));
+    users
+}
+
+/// Log every selected account in and return the ones that succeeded, and whether they all
+/// did.
+async fn connect_all(users: HashMap<String, User>, file_path: &Path) -> (Vec<Banana>, bool) {
+    let mut clients = vec![];
+    let mut ok = true;
+    for (name, user) in sorted(users) {
+        match connect(&name, user, file_path).await {
+            Ok(client) => clients.push(client),
+            Err(err) => {
+                eprintln!("{}: login err: {:?}", name, err);
+                ok = false;
+            }
+        }
+    }
+    (clients, ok)
+}
+
+/// Returns false when the account is unknown or couldn't log in.
+pub async fn login(alias: &str, users: HashMap<String, User>, file_path: &Path) -> bool {
+    let mut user = match users.get(alias) {
+        Some(user) => user.clone(),
+        None => {
+            eprintln!("unknown account: {}", alias);
+            return false;
+        }
+    };
+    match crate::login_account(alias, &mut user, file_path).await {
+        Ok(()) => {
+            println!("{}: logged in, tokens saved to {:?}", alias, file_path);
+            true
+        }
+        Err(err) => {
+            eprintln!("{}: login err: {:?}", alias, err);
+            false
+        }
+    }
+}
+
+/// How far `client` is from `goal`, "-" without one.
+async fn goal_progress(client: &Banana, goal: Option<&Goal>, peel: f64, usdt: f64) -> String {
+    let goal = match goal {
+        Some(goal) => goal,
+        None => return "-".to_string(),
+    };
+    let banana_ids = match goal.banana_id {
+        Some(_) => match client.owned_banana_ids().await {
+            Ok(ids) => ids,
+            Err(err) => return format!("get_banana_list err: {:?}", err),
+        },
+        None => vec![],
+    };
+    Progress::new(goal, peel, usdt, &banana_ids).to_string()
+}
+
+/// Returns false when an account couldn't log in or be fetched.
+pub async fn status(users: HashMap<String, User>, file_path: &Path) -> bool {
+    println!(
+        "{:<20} {:>12} {:>10} {:>9} {:>8} {:>11} {:>11} {:>11}  goal",
+        "account", "peel", "usdt", "clicks", "lottery", "token age", "expires in", "next claim"
+    );
+    let goals: HashMap<String, Goal> = users
+        .iter()
+        .filter_map(|(name, user)| Some((name.clone(), user.goal.clone()?)))
+        .collect();
+    let now = crate::utils::get_current_timestamp() / 1000;
+    let (clients, mut ok) = connect_all(users, file_path).await;
+    for client in clients {
+        let claims = client.token_claims();
+        let duration = |secs: Option<i64>| secs.map_or("-".to_string(), token::format_duration);
+        match client.get_user_info().await {
+            Ok(info) => println!(
+                "{:<20} {:>12.2} {:>10.4} {:>9} {:>8} {:>11} {:>11} {:>11}  {}",
+                client.name,
+                info.peel,
+                info.usdt,
+                format!("{}/{}", info.today_click_count, info.max_click_count),
+                info.lottery_info.remain_lottery_count,
+                duration(claims.and_then(|c| c.age(now))),
+                duration(claims.and_then(|c| c.expires_in(now))),
+                format!("{}s", next_claim_in(&info.lottery_info).max(0) / 1000),
+                goal_progress(&client, goals.get(&client.name), info.peel, info.usdt).await
+            ),
+            Err(err) => {
+                eprintln!("{}: get_user_info err: {:?}", client.name, err);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// Returns false when an account couldn't log in or be fetched.
+pub async fn inventory(users: HashMap<String, User>, file_path: &Path) -> bool {
+    let (clients, mut ok) = connect_all(users, file_path).await;
+    for client in clients {
+        println!("{}:", client.name);
+        match client.get_banana_list().await {
+            Ok(list) => {
+                for banana in list.iter().filter(|b| b.count > 0) {
+                    println!(
+                        "  {:>6}  {:<30} {:<12} x{}",
+                        banana.banana_id, banana.name, banana.ripeness, banana.count
+                    );
+                }
+            }
+            Err(err) => {
+                eprintln!("  get_banana_list err: {:?}", err);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// Returns false when an account couldn't log in or be fetched.
+pub async fn quests(users: HashMap<String, User>, file_path: &Path) -> bool {
+    let (clients, mut ok) = connect_all(users, file_path).await;
+    for client in clients {
+        println!("{}:", client.name);
+        match client.get_quest_list().await {
+            Ok(list) => {
+                for quest in list {
+                    let state = match (
+                        quest["is_achieved"].as_bool().unwrap_or_default(),
+                        quest["is_claimed"].as_bool().unwrap_or_default(),
+                    ) {
+                        (_, true) => "claimed",
+                        (true, false) => "achieved",
+                        _ => "todo",
+                    };
+                    println!(
+                        "  {:>6}  {:<9} {:<22} {}",
+                        quest["quest_id"].as_i64().unwrap_or_default(),
+                        state,
+                        quest["quest_type"].as_str().unwrap_or_default(),
+                        quest["quest_name"].as_str().unwrap_or_default()
+                    );
+                }
+            }
+            Err(err) => {
+                eprintln!("  get_quest_list err: {:?}", err);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// The link to store for what was pasted as one, and the invite code: the one given, else
+/// the referral code the link was opened with.
+fn import(link: &str, invite_code: Option<&str>) -> Result<(String, Option<String>), String> {
+    let link = init_data::normalize(link).map_err(|err| err.to_string())?;
+    let parsed = init_data::parse(&link).map_err(|err| err.to_string())?;
+    let missing = parsed.init_data.missing();
+    if !missing.is_empty() {
+        return Err(format!("tgWebAppData is missing {}", missing.join(", ")));
+    }
+    let invite_code = match invite_code {
+        Some(code) => init_data::invite_code(code),
+        None => parsed
+            .init_data
+            .start_param
+            .as_deref()
+            .map(init_data::referral_code),
+    };
+    Ok((link, invite_code))
+}
+
+pub fn accounts(command: &AccountsCommand, file_path: &Path) {
+    let result = match command {
+        AccountsCommand::List => config::load(file_path).map(|config| {
+            for (name, user) in sorted(config.accounts) {
+                let logged_in = user.access_token.is_some() && user.cookie_token.is_some();
+                let telegram_user = user
+                    .link
+                    .as_deref()
+                    .and_then(|link| init_data::parse(link).ok())
+                    .and_then(|link| link.init_data.user)
+                    .map(|user| user.to_string())
+                    .unwrap_or_else(|| "-".to_string());
+                println!(
+                    "{:<20} {:<14} {}",
+                    name,
+                    if logged_in {
+                        "logged in"
+                    } else {
+                        "not logged in"
+                    },
+                    telegram_user
+                );
+            }
+        }),
+        AccountsCommand::Add {
+            alias,
+            link,
+            invite_code,
+        } => match config::load(file_path) {
+            Ok(config) if config.accounts.contains_key(alias) => {
+                eprintln!("account {} already exists", alias);
+                std::process::exit(1);
+            }
+            Ok(_) | Err(ConfigError::Missing(_)) => match import(link, invite_code.as_deref()) {
+                Ok((link, invite_code)) => {
+                    let user = User {
+                        link: Some(link),
+                        access_token: None,
+                        cookie_token: None,
+                        invite_code,
+                        goal: None,
+                        proxy: None,
+                        features: Default::default(),
+                    };
+                    config::save_account(file_path, alias, &user)
+                        .map(|()| println!("account {} added", alias))
+                }
+                Err(err) => {
+                    eprintln!("{}: {}", alias, err);
+                    std::process::exit(1);
+                }
+            },
+            Err(err) => Err(err),
+        },
+        AccountsCommand::Remove { alias } => {
+            config::remove_account(file_path, alias).map(|removed| match removed {
+                true => {
+                    crate::cookies::remove(alias);
+                    println!("account {} removed", alias)
+                }
+                false => {
+                    eprintln!("unknown account: {}", alias);
+                    std::process::exit(1);
+                }
+            })
+        }
+    };
+    if let Err(err) = result {
+        config::explain(&err);
+        std::process::exit(1);
+    }
+}
+
+pub fn encrypt(file_path: &Path) {
+    if !crate::secrets::is_enabled() {
+        eprintln!("set BANANA_SECRETS_FILE to the encrypted store to create or use");
+        std::process::exit(1);
+    }
+    let result = config::load(file_path).and_then(|config| {
+        for (alias, user) in sorted(config.accounts) {
+            config::save_account(file_path, &alias, &user)?;
+        }
+        Ok(())
+    });
+    if let Err(err) = result {
+        config::explain(&err);
+        std::process::exit(1);
+    }
+    println!("secrets moved out of {:?}", file_path);
+}
@@ -0,0 +1,820 @@
+use crate::utils::User;
+use crate::{control, secrets};
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tokio::time::sleep;
+use toml_edit::{DocumentMut, Item, Table, TableLike};
+
+const JSON_TEMPLATE: &str = r#"{
+    "alias1": {
+        "link": "paste your banana link here",
+        "invite_code": ""
+    }
+}
+"#;
+
+const TOML_TEMPLATE: &str = r#"[global]
+# proxy = "http://127.0.0.1:8080"
+# log_format = "json"
+
+[accounts.alias1]
+link = "paste your banana link here"
+invite_code = ""
+"#;
+
+const YAML_TEMPLATE: &str = r#"global:
+  # proxy: http://127.0.0.1:8080
+  # log_format: json
+accounts:
+  alias1:
+    link: paste your banana link here
+    invite_code: ""
+"#;
+
+/// Held across every read-merge-write of the config and the secrets store: accounts save their
+/// tokens from their own tasks and would otherwise overwrite each other's.
+static WRITES: Mutex<()> = Mutex::new(());
+
+/// Tells apart the temporary files of writes in progress.
+static TMP_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Missing(PathBuf),
+    Invalid(PathBuf, String),
+    Io(PathBuf, std::io::Error),
+    Secrets(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Missing(path) => write!(f, "config {:?} not found", path),
+            ConfigError::Invalid(path, err) => write!(f, "config {:?} is invalid: {}", path, err),
+            ConfigError::Io(path, err) => write!(f, "can't access config {:?}: {}", path, err),
+            ConfigError::Secrets(err) => write!(f, "can't save the secrets: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    fn of(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Some(Format::Json),
+            Some("toml") => Some(Format::Toml),
+            Some("yaml") | Some("yml") => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+
+    fn parse(self, content: &str) -> Result<Value, String> {
+        match self {
+            Format::Json => serde_json::from_str(content).map_err(|err| err.to_string()),
+            Format::Toml => toml::from_str(content).map_err(|err| err.to_string()),
+            Format::Yaml => serde_yaml::from_str(content).map_err(|err| err.to_string()),
+        }
+    }
+
+    fn render<T: Serialize>(self, value: &T) -> Result<String, String> {
+        match self {
+            Format::Json => serde_json::to_string_pretty(value).map_err(|err| err.to_string()),
+            Format::Toml => toml::to_string_pretty(value).map_err(|err| err.to_string()),
+            Format::Yaml => serde_yaml::to_string(value).map_err(|err| err.to_string()),
+        }
+    }
+
+    fn template(self) -> &'static str {
+        match self {
+            Format::Json => JSON_TEMPLATE,
+            Format::Toml => TOML_TEMPLATE,
+            Format::Yaml => YAML_TEMPLATE,
+        }
+    }
+}
+
+/// Settings of the `global` section, named like the `BANANA_*` variables without the prefix,
+/// e.g. `log_format` or `proxy`.
+pub type Global = BTreeMap<String, Value>;
+
+#[derive(Default)]
+pub struct Config {
+    pub global: Global,
+    pub accounts: HashMap<String, User>,
+}
+
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    global: Global,
+    #[serde(default)]
+    accounts: BTreeMap<String, User>,
+}
+
+fn read_file(path: &Path) -> Result<Value, ConfigError> {
+    let content = fs::read_to_string(path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => ConfigError::Missing(path.to_path_buf()),
+        _ => ConfigError::Io(path.to_path_buf(), err),
+    })?;
+    Format::of(path)
+        .unwrap_or(Format::Json)
+        .parse(&content)
+        .map_err(|err| ConfigError::Invalid(path.to_path_buf(), err))
+}
+
+fn from_value<T: serde::de::DeserializeOwned>(path: &Path, value: Value) -> Result<T, ConfigError> {
+    serde_json::from_value(value)
+        .map_err(|err| ConfigError::Invalid(path.to_path_buf(), err.to_string()))
+}
+
+fn load_file(path: &Path) -> Result<Config, ConfigError> {
+    let value = read_file(path)?;
+    if value.get("accounts").is_none() && value.get("global").is_none() {
+        return Ok(Config {
+            accounts: from_value(path, value)?,
+            ..Default::default()
+        });
+    }
+    let file: ConfigFile = from_value(path, value)?;
+    Ok(Config {
+        global: file.global,
+        accounts: file.accounts.into_iter().collect(),
+    })
+}
+
+/// Files of a config directory: `global.{ext}` and one `{alias}.{ext}` per account.
+fn dir_files(dir: &Path) -> Result<Vec<(String, PathBuf)>, ConfigError> {
+    let entries = fs::read_dir(dir).map_err(|err| ConfigError::Io(dir.to_path_buf(), err))?;
+    let mut files = vec![];
+    for entry in entries.flatten() {
+        let file = entry.path();
+        if Format::of(&file).is_none() {
+            continue;
+        }
+        if let Some(stem) = file.file_stem().and_then(|s| s.to_str()) {
+            files.push((stem.to_string(), file.clone()));
+        }
+    }
+    Ok(files)
+}
+
+fn load_dir(dir: &Path) -> Result<Config, ConfigError> {
+    let mut config = Config::default();
+    for (stem, file) in dir_files(dir)? {
+        let value = read_file(&file)?;
+        if stem == "global" {
+            config.global = from_value(&file, value)?;
+        } else {
+            config.accounts.insert(stem, from_value(&file, value)?);
+        }
+    }
+    Ok(config)
+}
+
+/// One file of the config as written, for `validate`.
+pub struct Source {
+    pub file: PathBuf,
+    pub content: String,
+    /// the parsed file or why it couldn't be
+    pub value: Result<Value, String>,
+    /// stem of the file in a config directory: `global` or an alias
+    pub stem: Option<String>,
+}
+
+/// The files making up the config at `path`.
+pub fn sources(path: &Path) -> Result<Vec<Source>, ConfigError> {
+    let files = match path.is_dir() {
+        true => dir_files(path)?
+            .into_iter()
+            .map(|(stem, file)| (Some(stem), file))
+            .collect(),
+        false => vec![(None, path.to_path_buf())],
+    };
+    let mut sources = vec![];
+    for (stem, file) in files {
+        let content = fs::read_to_string(&file).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => ConfigError::Missing(file.clone()),
+            _ => ConfigError::Io(file.clone(), err),
+        })?;
+        let value = Format::of(&file).unwrap_or(Format::Json).parse(&content);
+        sources.push(Source {
+            file,
+            content,
+            value,
+            stem,
+        });
+    }
+    sources.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(sources)
+}
+
+/// The config at `path`: a JSON, TOML or YAML file with `global` and `accounts` sections or
+/// in the legacy user.json shape, or a directory with one file per account.
+/// Links and tokens kept in the secrets store are filled in.
+pub fn load(path: &Path) -> Result<Config, ConfigError> {
+    let mut config = if path.is_dir() {
+        load_dir(path)?
+    } else {
+        load_file(path)?
+    };
+    secrets::fill(&mut config.accounts);
+    Ok(config)
+}
+
+/// Replace `path` with `content` without ever leaving it half written: the content goes to a
+/// temporary file next to it which is then renamed over it. The previous content is kept as
+/// `{path}.bak.1`, older ones are shifted up to `BANANA_CONFIG_BACKUPS`. The permissions of
+/// `path` are kept, a new file is only readable by its owner.
+pub fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("config");
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let tmp = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        name,
+        std::process::id(),
+        TMP_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&tmp)?;
+    // the file may hold tokens, it keeps whatever access its owner gave it
+    if let Ok(metadata) = fs::metadata(path) {
+        file.set_permissions(metadata.permissions())?;
+    }
+    file.write_all(content)?;
+    file.sync_all()?;
+
+    let backups = crate::settings::get().config_backups;
+    if backups > 0 && path.exists() {
+        let backup = |n: usize| dir.join(format!("{}.bak.{}", name, n));
+        for n in (1..backups).rev() {
+            if backup(n).exists() {
+                fs::rename(backup(n), backup(n + 1))?;
+            }
+        }
+        fs::copy(path, backup(1))?;
+    }
+    fs::rename(&tmp, path)
+}
+
+/// The file at `path` as a document, `None` when it doesn't exist yet.
+fn read_document(path: &Path) -> Result<Option<Value>, ConfigError> {
+    match read_file(path) {
+        Ok(value) => Ok(Some(value)),
+        Err(ConfigError::Missing(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// The JSON value of a TOML item, `None` for one it can't be.
+fn toml_json(item: &Item) -> Option<Value> {
+    let value = item.clone().into_value().ok()?;
+    Value::deserialize(value.into_deserializer()).ok()
+}
+
+/// Bring `table` to `value` touching only the keys that changed, so that comments and layout
+/// stay as written. New maps become `[sections]` under a `standard` table. TOML has no null,
+/// keys set to one are left out.
+fn sync_toml(
+    table: &mut dyn TableLike,
+    value: &Map<String, Value>,
+    standard: bool,
+) -> Result<(), String> {
+    let gone: Vec<String> = table
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| value.get(key).is_none_or(Value::is_null))
+        .collect();
+    for key in gone {
+        table.remove(&key);
+    }
+
+    for (key, new) in value.iter().filter(|(_, new)| !new.is_null()) {
+        if let Some(item) = table.get_mut(key) {
+            if toml_json(item).as_ref() == Some(new) {
+                continue;
+            }
+            if let (Some(map), true) = (new.as_object(), item.is_table_like()) {
+                let standard = item.is_table();
+                sync_toml(item.as_table_like_mut().unwrap(), map, standard)?;
+                continue;
+            }
+        }
+        let mut item = match new {
+            Value::Object(map) if standard => {
+                let mut section = Table::new();
+                section.set_implicit(true);
+                sync_toml(&mut section, map, true)?;
+                Item::Table(section)
+            }
+            new => Item::Value(
+                new.serialize(toml_edit::ser::ValueSerializer::new())
+                    .map_err(|err| err.to_string())?,
+            ),
+        };
+        match table.get_mut(key) {
+            Some(old) => {
+                // keep the comment at the end of the line
+                if let (Some(old), Some(value)) = (old.as_value(), item.as_value_mut()) {
+                    *value.decor_mut() = old.decor().clone();
+                }
+                *old = item;
+            }
+            None => {
+                table.insert(key, item);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `document` as the TOML file at `path`, edited in place when it exists.
+fn render_toml(path: &Path, document: &Value) -> Result<String, String> {
+    let mut toml = match fs::read_to_string(path) {
+        Ok(content) => content
+            .parse::<DocumentMut>()
+            .map_err(|err| err.to_string())?,
+        Err(_) => DocumentMut::new(),
+    };
+    let map = document.as_object().ok_or("expected a map")?;
+    sync_toml(toml.as_table_mut(), map, true)?;
+    Ok(toml.to_string())
+}
+
+/// Write `document` to the file at `path`. TOML files keep their comments, JSON and YAML
+/// files are rendered again and lose them.
+fn write_document(path: &Path, document: &Value) -> Result<(), ConfigError> {
+    let content = match Format::of(path).unwrap_or(Format::Json) {
+        Format::Toml => render_toml(path, document),
+        format => format.render(document),
+    }
+    .map_err(|err| ConfigError::Invalid(path.to_path_buf(), err))?;
+    write_atomic(path, content.as_bytes()).map_err(|err| ConfigError::Io(path.to_path_buf(), err))
+}
+
+/// The accounts object of a config file document, `{alias: account}` in the legacy shape.
+fn accounts_of<'a>(
+    path: &Path,
+    document: &'a mut Value,
+) -> Result<&'a mut Map<String, Value>, ConfigError> {
+    let invalid = || ConfigError::Invalid(path.to_path_buf(), "expected a map of accounts".into());
+    let root = document.as_object_mut().ok_or_else(invalid)?;
+    if !root.contains_key("accounts") && !root.contains_key("global") {
+        return Ok(root);
+    }
+    root.entry("accounts")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(invalid)
+}
+
+/// Write `user` over `account`, keeping the fields we don't know about and the order of the
+/// ones already there. Known fields `user` leaves out or has no value for, like switched on
+/// features or the secrets moved to the store, are dropped.
+fn merge(account: &mut Value, user: &User) {
+    if !account.is_object() {
+        *account = Value::Object(Map::new());
+    }
+    let account = account.as_object_mut().unwrap();
+    let user = match serde_json::to_value(user) {
+        Ok(Value::Object(user)) => user,
+        _ => return,
+    };
+    account.retain(|key, _| !User::FIELDS.contains(&key.as_str()) || user.contains_key(key));
+    for (key, value) in user {
+        if value.is_null() {
+            account.shift_remove(&key);
+            continue;
+        }
+        account.insert(key, value);
+    }
+}
+
+/// `user` as written to the config: without its link and tokens when the secrets store is
+/// enabled, they go to the store instead. The store is saved first so that a config that
+/// can't be written still holds them.
+fn stripped(alias: &str, user: &User) -> Result<User, ConfigError> {
+    let mut user = user.clone();
+    secrets::take(alias, &mut user);
+    secrets::save().map_err(ConfigError::Secrets)?;
+    Ok(user)
+}
+
+/// Save the secrets store on its own, e.g. for the cookie jars kept in it.
+pub fn save_secrets() -> Result<(), ConfigError> {
+    let _writes = WRITES.lock().unwrap();
+    secrets::save().map_err(ConfigError::Secrets)
+}
+
+/// The file of `alias` in a config directory, a new JSON file if it has none yet.
+fn account_file(dir: &Path, alias: &str) -> Result<PathBuf, ConfigError> {
+    Ok(dir_files(dir)?
+        .into_iter()
+        .find(|(stem, _)| stem == alias)
+        .map(|(_, file)| file)
+        .unwrap_or_else(|| dir.join(format!("{}.json", alias))))
+}
+
+/// Add or replace one account in the config at `path`, leaving the rest of it as it was.
+pub fn save_account(path: &Path, alias: &str, user: &User) -> Result<(), ConfigError> {
+    let _writes = WRITES.lock().unwrap();
+    write_account(path, alias, &stripped(alias, user)?)
+}
+
+fn write_account(path: &Path, alias: &str, user: &User) -> Result<(), ConfigError> {
+    edit_account(path, alias, true, |account| {
+        merge(account, user);
+        true
+    })
+}
+
+/// Apply `edit` to the account `alias` as it is in the config now and write it back if
+/// `edit` changed it. A missing account is only added when `create` is set.
+fn edit_account(
+    path: &Path,
+    alias: &str,
+    create: bool,
+    edit: impl FnOnce(&mut Value) -> bool,
+) -> Result<(), ConfigError> {
+    if path.is_dir() {
+        let file = account_file(path, alias)?;
+        let mut account = match read_document(&file)? {
+            Some(account) => account,
+            None if create => Value::Null,
+            None => return Ok(()),
+        };
+        return match edit(&mut account) {
+            true => write_document(&file, &account),
+            false => Ok(()),
+        };
+    }
+
+    let mut document = read_document(path)?.unwrap_or_else(|| match Format::of(path) {
+        Some(Format::Json) | None => json!({}),
+        Some(_) => json!({ "accounts": {} }),
+    });
+    let accounts = accounts_of(path, &mut document)?;
+    if !create && !accounts.contains_key(alias) {
+        return Ok(());
+    }
+    match edit(accounts.entry(alias).or_insert(Value::Null)) {
+        true => write_document(path, &document),
+        false => Ok(()),
+    }
+}
+
+/// Replace the tokens of `alias` after it logged in, leaving the rest of its account as it
+/// is in the config now, hand edits made since it was loaded included. With the secrets
+/// store enabled the tokens go there and the config only loses the ones it still had.
+pub fn save_tokens(
+    path: &Path,
+    alias: &str,
+    access_token: &str,
+    cookie_token: &str,
+) -> Result<(), ConfigError> {
+    let _writes = WRITES.lock().unwrap();
+    let store = secrets::is_enabled();
+    if store {
+        secrets::set_tokens(alias, access_token, cookie_token);
+        secrets::save().map_err(ConfigError::Secrets)?;
+    }
+    edit_account(path, alias, false, |account| {
+        let account = match account.as_object_mut() {
+            Some(account) => account,
+            None => return false,
+        };
+        let tokens = [
+            ("access_token", access_token),
+            ("cookie_token", cookie_token),
+        ];
+        let mut changed = false;
+        for (key, token) in tokens {
+            if store {
+                changed |= account.shift_remove(key).is_some();
+            } else if account.get(key).and_then(Value::as_str) != Some(token) {
+                account.insert(key.to_string(), token.into());
+                changed = true;
+            }
+        }
+        changed
+    })
+}
+
+/// Remove one account from the config at `path`, returns false if it wasn't there.
+pub fn remove_account(path: &Path, alias: &str) -> Result<bool, ConfigError> {
+    let _writes = WRITES.lock().unwrap();
+    secrets::remove(alias);
+    secrets::save().map_err(ConfigError::Secrets)?;
+    if path.is_dir() {
+        let file = account_file(path, alias)?;
+        return match fs::remove_file(&file) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(ConfigError::Io(file, err)),
+        };
+    }
+
+    let mut document = read_file(path)?;
+    if accounts_of(path, &mut document)?
+        .shift_remove(alias)
+        .is_none()
+    {
+        return Ok(false);
+    }
+    write_document(path, &document)?;
+    Ok(true)
+}
+
+/// What changes when the config is edited: size and modification time of the file, or of
+/// every file of a config directory.
+fn fingerprint(path: &Path) -> Vec<(PathBuf, u64, Option<SystemTime>)> {
+    let files = match dir_files(path) {
+        Ok(files) => files.into_iter().map(|(_, file)| file).collect(),
+        Err(_) => vec![path.to_path_buf()],
+    };
+    let mut fingerprint: Vec<_> = files
+        .into_iter()
+        .filter_map(|file| {
+            let metadata = fs::metadata(&file).ok()?;
+            Some((file, metadata.len(), metadata.modified().ok()))
+        })
+        .collect();
+    fingerprint.sort();
+    fingerprint
+}
+
+/// Ask `main()` to reload the accounts whenever the config at `path` changes, checking every
+/// `interval`.
+pub async fn watch(path: PathBuf, interval: Duration) {
+    let mut last = fingerprint(&path);
+    loop {
+        sleep(interval).await;
+        let current = fingerprint(&path);
+        if current != last {
+            last = current;
+            log::debug!("config {:?} changed", path);
+            control::send(control::Action::Reload);
+        }
+    }
+}
+
+/// Print why the config couldn't be loaded. When it is missing and we run in a terminal,
+/// offer to write a template to fill in.
+pub fn explain(err: &ConfigError) {
+    eprintln!("{}", err);
+    let path = match err {
+        ConfigError::Missing(path) => path,
+        _ => return,
+    };
+    eprintln!("set its location with --config <path> or BANANA_CONFIG, or add an account with `accounts add <alias> <link>`");
+    if !std::io::stdin().is_terminal() {
+        return;
+    }
+
+    eprint!("create a template at {:?}? [y/N] ", path);
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    let _ = std::io::stdin().lock().read_line(&mut answer);
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        return;
+    }
+    let template = Format::of(path).unwrap_or(Format::Json).template();
+    match fs::write(path, template) {
+        Ok(()) => eprintln!("template written, put your banana link in it and start again"),
+        Err(err) => eprintln!("can't write {:?}: {}", path, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINK: &str = "https://banana.carv.io/#tgWebAppData=query_id%3Dq";
+
+    fn user() -> User {
+        User {
+            link: Some(LINK.to_string()),
+            access_token: Some("new-token".to_string()),
+            cookie_token: Some("banana-game:user:token=new".to_string()),
+            invite_code: None,
+            goal: None,
+            proxy: None,
+            features: Default::default(),
+        }
+    }
+
+    /// `user` once `secrets::take` moved its link and tokens to the store.
+    fn without_secrets() -> User {
+        User {
+            link: None,
+            access_token: None,
+            cookie_token: None,
+            ..user()
+        }
+    }
+
+    /// A fresh file named `name` with `content` in a directory of its own.
+    fn file(test: &str, name: &str, content: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("banana-config-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn account(path: &Path, alias: &str) -> Value {
+        let mut document = read_file(path).unwrap();
+        accounts_of(path, &mut document).unwrap()[alias].clone()
+    }
+
+    #[test]
+    fn merge_keeps_unknown_keys_and_order() {
+        let mut account = json!({
+            "note": "mine",
+            "access_token": "old",
+            "link": "old link",
+            "click": false,
+        });
+        merge(&mut account, &user());
+        let keys: Vec<&String> = account.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["note", "access_token", "link", "cookie_token"]);
+        assert_eq!(account["access_token"], "new-token");
+        assert_eq!(account["link"], LINK);
+    }
+
+    #[test]
+    fn merge_drops_secrets_moved_to_the_store() {
+        let mut account = json!({
+            "link": "old link",
+            "access_token": "old",
+            "cookie_token": "old",
+            "invite_code": "ABC",
+            "note": "mine",
+        });
+        merge(&mut account, &without_secrets());
+        assert_eq!(account, json!({ "note": "mine" }));
+    }
+
+    #[test]
+    fn json_account_is_updated_in_place() {
+        let path = file(
+            "json",
+            "user.json",
+            r#"{"b": {"link": "b"}, "a": {"note": "mine", "access_token": "old"}}"#,
+        );
+        write_account(&path, "a", &user()).unwrap();
+        let document = read_file(&path).unwrap();
+        let aliases: Vec<&String> = document.as_object().unwrap().keys().collect();
+        assert_eq!(aliases, ["b", "a"]);
+        assert_eq!(document["a"]["note"], "mine");
+        assert_eq!(document["a"]["access_token"], "new-token");
+
+        write_account(&path, "a", &without_secrets()).unwrap();
+        assert_eq!(account(&path, "a"), json!({ "note": "mine" }));
+    }
+
+    #[test]
+    fn tokens_keep_hand_edits() {
+        let path = file(
+            "tokens",
+            "user.json",
+            r#"{"a": {"link": "edited", "proxy": "socks5://edited", "access_token": "old"}}"#,
+        );
+        save_tokens(&path, "a", "new-token", "new-cookie").unwrap();
+        assert_eq!(
+            account(&path, "a"),
+            json!({
+                "link": "edited",
+                "proxy": "socks5://edited",
+                "access_token": "new-token",
+                "cookie_token": "new-cookie"
+            })
+        );
+
+        save_tokens(&path, "removed", "new-token", "new-cookie").unwrap();
+        assert!(read_file(&path).unwrap().get("removed").is_none());
+    }
+
+    #[test]
+    fn toml_account_keeps_comments() {
+        let path = file(
+            "toml",
+            "user.toml",
+            r#"# my accounts
+[global]
+log_format = "json"
+
+[accounts.a]
+link = "old link"
+access_token = "old" # refreshed by the bot
+note = "mine"
+
+[accounts.a.goal]
+peel = 100.0
+action = "sell"
+"#,
+        );
+        let mut user = user();
+        user.goal = serde_json::from_value(account(&path, "a")["goal"].clone()).ok();
+        write_account(&path, "a", &user).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# my accounts\n[global]\n"));
+        assert!(content.contains("access_token = \"new-token\" # refreshed by the bot\n"));
+        assert!(content.contains("[accounts.a.goal]\npeel = 100.0\n"));
+        assert_eq!(account(&path, "a")["note"], "mine");
+
+        write_account(&path, "a", &without_secrets()).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# my accounts\n"));
+        assert!(!content.contains("link") && !content.contains("token"));
+        assert_eq!(account(&path, "a")["note"], "mine");
+    }
+
+    #[test]
+    fn toml_account_is_added_as_a_section() {
+        let path = file(
+            "toml-new",
+            "user.toml",
+            "# my accounts\n[global]\nproxy = \"\"\n",
+        );
+        write_account(&path, "a", &user()).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# my accounts\n"));
+        assert!(content.contains("[accounts.a]\n"));
+        assert_eq!(account(&path, "a")["link"], LINK);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_keep_the_file_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let path = file("mode", "user.json", "{}");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        write_account(&path, "a", &user()).unwrap();
+        assert_eq!(mode(&path), 0o640);
+
+        let new = path.with_file_name("new.json");
+        write_account(&new, "a", &user()).unwrap();
+        assert_eq!(mode(&new), 0o600);
+    }
+
+    #[test]
+    fn concurrent_saves_keep_every_account() {
+        let path = file("concurrent", "user.json", "{}");
+        let threads: Vec<_> = (0..8)
+            .map(|n| {
+                let path = path.clone();
+                std::thread::spawn(move || save_account(&path, &format!("a{}", n), &user()))
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap().unwrap();
+        }
+        for n in 0..8 {
+            assert_eq!(
+                account(&path, &format!("a{}", n))["access_token"],
+                "new-token"
+            );
+        }
+    }
+
+    #[test]
+    fn yaml_account_is_updated() {
+        let path = file(
+            "yaml",
+            "user.yaml",
+            "accounts:\n  a:\n    note: mine\n    access_token: old\n",
+        );
+        write_account(&path, "a", &user()).unwrap();
+        assert_eq!(account(&path, "a")["access_token"], "new-token");
+        assert_eq!(account(&path, "a")["note"], "mine");
+
+        write_account(&path, "a", &without_secrets()).unwrap();
+        assert_eq!(account(&path, "a"), json!({ "note": "mine" }));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::http::{Request, Response};
+use crate::state::{self, Command};
+use serde_json::json;
+use std::sync::OnceLock;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+/// A request handled by `main()` rather than by one account's task.
+pub enum Action {
+    Reload,
+    Shutdown,
+}
+
+static ACTIONS: OnceLock<UnboundedSender<Action>> = OnceLock::new();
+
+/// Create the channel of process-wide actions; `main()` owns the receiving end.
+pub fn init() -> UnboundedReceiver<Action> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    ACTIONS.set(tx).expect("control already initialized");
+    rx
+}
+
+/// Hand `action` to `main()`, false if it isn't listening.
+pub fn send(action: Action) -> bool {
+    ACTIONS.get().is_some_and(|tx| tx.send(action).is_ok())
+}
+
+fn dispatch(action: Action) -> Response {
+    match send(action) {
+        true => ok(),
+        false => Response::json(500, &json!({ "error": "main loop is not running" })),
+    }
+}
+
+fn ok() -> Response {
+    Response::json(200, &json!({ "ok": true }))
+}
+
+fn account_action(account: &str, action: &str) -> Response {
+    if !state::snapshot().contains_key(account) {
+        return Response::json(404, &json!({ "error": "unknown account" }));
+    }
+    let command = match action {
+        "pause" => {
+            state::pause(account);
+            return ok();
+        }
+        "resume" => {
+            state::resume(account);
+            return ok();
+        }
+        "click" => Command::Click,
+        "claim" => Command::Claim,
+        "lottery" => Command::Lottery,
+        "quests" => Command::Quests,
+        _ => return Response::not_found(),
+    };
+    state::send_command(account, command);
+    ok()
+}
+
+/// Why `request` is refused, if it is. Browsers send simple cross-origin POSTs without a
+/// preflight, so requests from a web page (they carry an `Origin`) never get through, and
+/// with `BANANA_CONTROL_TOKEN` set every request must bring it.
+fn refuse(request: &Request) -> Option<Response> {
+    if request.header("origin").is_some() {
+        return Some(Response::json(
+            403,
+            &json!({ "error": "requests from web pages are refused" }),
+        ));
+    }
+    let token = crate::settings::get().control_token.as_deref()?;
+    let bearer = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match bearer == Some(token) {
+        true => None,
+        false => Some(Response::json(
+            401,
+            &json!({ "error": "missing or wrong Authorization: Bearer token" }),
+        )),
+    }
+}
+
+pub async fn handle(request: Request) -> Response {
+    if let Some(response) = refuse(&request) {
+        return response;
+    }
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["accounts"]) => Response::json(
+            200,
+            &serde_json::to_value(state::snapshot()).unwrap_or_default(),
+        ),
+        ("POST", ["accounts", account, action]) => account_action(account, action),
+        ("POST", ["reload"]) => dispatch(Action::Reload),
+        ("POST", ["shutdown"]) => dispatch(Action::Shutdown),
+        _ => Response::not_found(),
+    }
+}
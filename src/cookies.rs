@@ -0,0 +1,136 @@
+use cookie_store::{CookieStore, RawCookie};
+use reqwest::header::{HeaderMap, HeaderValue, SET_COOKIE};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+/// The cookies of every account, loaded from `{cookies_dir}/{account}.json` on first use.
+static JARS: LazyLock<Mutex<HashMap<String, CookieStore>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn path(account: &str) -> PathBuf {
+    crate::settings::get()
+        .cookies_dir
+        .join(format!("{}.json", account))
+}
+
+/// Cookies that have expired are dropped while loading. With the secrets store enabled the
+/// jar is kept in it, a jar file left from before is read once and removed on the next save.
+fn load(account: &str) -> CookieStore {
+    let loaded = match crate::secrets::cookies(account) {
+        Some(jar) => cookie_store::serde::json::load(jar.as_bytes()),
+        None => match fs::File::open(path(account)) {
+            Ok(file) => cookie_store::serde::json::load(BufReader::new(file)),
+            Err(_) => return CookieStore::default(),
+        },
+    };
+    loaded.unwrap_or_else(|err| {
+        crate::utils::format_error(account, &format!("cookies err: {}", err));
+        CookieStore::default()
+    })
+}
+
+/// Write the jar where [`load`] reads it from, the file is only readable by its owner.
+fn write(account: &str, path: &Path, jar: &[u8]) -> std::io::Result<()> {
+    if crate::secrets::is_enabled() {
+        crate::secrets::set_cookies(account, Some(String::from_utf8_lossy(jar).into_owned()));
+        crate::config::save_secrets().map_err(std::io::Error::other)?;
+        return match fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        };
+    }
+
+    fs::create_dir_all(path.parent().unwrap_or(path))?;
+    let tmp = path.with_extension("json.tmp");
+    let _ = fs::remove_file(&tmp);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(&tmp)?.write_all(jar)?;
+    fs::rename(&tmp, path)
+}
+
+/// Session cookies are kept too, the API doesn't set an expiry on its token.
+fn save(account: &str, jar: &CookieStore) {
+    let path = path(account);
+    let mut json = vec![];
+    let result = cookie_store::serde::json::save_incl_expired_and_nonpersistent(jar, &mut json)
+        .map_err(std::io::Error::other)
+        .and_then(|()| write(account, &path, &json));
+    if let Err(err) = result {
+        crate::utils::format_error(account, &format!("save cookies {:?} err: {}", path, err));
+    }
+}
+
+fn with_jar<T>(account: &str, f: impl FnOnce(&mut CookieStore) -> T) -> T {
+    let mut jars = JARS.lock().unwrap();
+    let jar = jars
+        .entry(account.to_string())
+        .or_insert_with(|| load(account));
+    f(jar)
+}
+
+/// The `Cookie` header for a request of `account` to `url`, `None` when no cookie matches.
+pub fn header(account: &str, url: &Url) -> Option<HeaderValue> {
+    let value = with_jar(account, |jar| {
+        jar.get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ")
+    });
+    HeaderValue::from_str(&value).ok().filter(|v| !v.is_empty())
+}
+
+/// Keep the cookies set by a response to `url`, honouring their domain, path and expiry.
+pub fn store(account: &str, url: &Url, headers: &HeaderMap) {
+    let cookies: Vec<RawCookie<'static>> = headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| RawCookie::parse(value.to_string()).ok())
+        .collect();
+    if cookies.is_empty() {
+        return;
+    }
+    with_jar(account, |jar| {
+        jar.store_response_cookies(cookies.into_iter(), url);
+        save(account, jar);
+    });
+}
+
+/// Start from the `name=value` cookie of the config when the jar doesn't have it yet, as
+/// for accounts that logged in before cookies were kept.
+pub fn seed(account: &str, url: &Url, cookie: &str) {
+    let cookie = match RawCookie::parse(cookie.to_string()) {
+        Ok(cookie) => cookie,
+        Err(_) => return,
+    };
+    with_jar(account, |jar| {
+        if jar
+            .get_request_values(url)
+            .any(|(name, _)| name == cookie.name())
+        {
+            return;
+        }
+        if jar.insert_raw(&cookie, url).is_ok() {
+            save(account, jar);
+        }
+    });
+}
+
+/// Forget the cookies of a removed account.
+pub fn remove(account: &str) {
+    JARS.lock().unwrap().remove(account);
+    let _ = fs::remove_file(path(account));
+    if crate::secrets::cookies(account).is_some() {
+        crate::secrets::set_cookies(account, None);
+        if let Err(err) = crate::config::save_secrets() {
+            crate::utils::format_error(account, &err.to_string());
+        }
+    }
+}
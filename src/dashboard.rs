@@ -0,0 +1,101 @@
+use crate::http::{Request, Response};
+use crate::state::{self, AccountState};
+use chrono::{Local, TimeZone};
+use std::fmt::Write as _;
+
+// seconds between two refreshes of the page
+const REFRESH_SECS: u32 = 10;
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn format_time(timestamp: Option<i64>) -> String {
+    timestamp
+        .and_then(|t| Local.timestamp_millis_opt(t).single())
+        .map(|t| t.format("%F %T").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn format_balance(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |v| format!("{:.2}", v))
+}
+
+fn row(html: &mut String, name: &str, s: &AccountState, now: i64) {
+    let next_claim = match s.next_claim {
+        Some(t) => format!("{} ({}s)", format_time(Some(t)), ((t - now).max(0) / 1000)),
+        None => "-".to_string(),
+    };
+    let last_error = match &s.last_error {
+        Some(err) => format!("{} {}", format_time(s.last_error_at), escape(err)),
+        None => "-".to_string(),
+    };
+    let _ = writeln!(
+        html,
+        "<tr><td>{}</td><td class=\"{:?}\">{:?}</td><td>{}</td><td>{}</td><td>{}/{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+        escape(name),
+        s.status,
+        s.status,
+        format_balance(s.peel),
+        format_balance(s.usdt),
+        s.today_click_count,
+        s.max_click_count,
+        s.remain_lottery_count,
+        next_claim,
+        escape(s.goal.as_deref().unwrap_or("-")),
+        last_error
+    );
+}
+
+pub fn render() -> String {
+    let now = crate::utils::get_current_timestamp();
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="{}">
+<title>Banana Bot 🍌</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #ddd; padding: 4px 8px; text-align: left; }}
+th {{ background: #fff3b0; }}
+.Running {{ color: green; }}
+.Failed {{ color: red; }}
+.Paused {{ color: gray; }}
+</style>
+</head>
+<body>
+<h1>Banana Bot 🍌</h1>
+<p>updated at {}</p>
+<table>
+<tr><th>account</th><th>status</th><th>peel</th><th>usdt</th><th>clicks today</th><th>lottery left</th><th>next claim</th><th>goal</th><th>last error</th></tr>
+"#,
+        REFRESH_SECS,
+        crate::utils::now()
+    );
+    for (name, s) in state::snapshot() {
+        row(&mut html, &name, &s, now);
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+
+    html
+}
+
+pub async fn handle(request: Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => Response::new(200, "text/html; charset=utf-8", render()),
+        ("GET", "/api/accounts") => Response::json(
+            200,
+            &serde_json::to_value(state::snapshot()).unwrap_or_default(),
+        ),
+        _ => Response::not_found(),
+    }
+}
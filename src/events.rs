@@ -0,0 +1,31 @@
+use crate::{metrics, notify, state, summary, BananaUserInfo};
+
+/// Something that happened to an account, fanned out to the daily summary, the metrics,
+/// the account state and the notifications.
+pub enum Event<'a> {
+    ClickLimit(i32),
+    Clicked(i32),
+    Claimed,
+    SpeedUp,
+    Harvested {
+        name: &'a str,
+        ripeness: &'a str,
+    },
+    QuestClaimed,
+    AdsIncome(f64),
+    /// a fresh profile from `get_user_info`
+    UserInfo(&'a BananaUserInfo),
+    /// timestamp (ms) of the next claim
+    NextClaim(i64),
+    Retry {
+        endpoint: &'a str,
+    },
+    Error(&'a str),
+}
+
+pub fn emit(account: &str, event: Event) {
+    summary::on_event(account, &event);
+    metrics::on_event(account, &event);
+    state::on_event(account, &event);
+    notify::on_event(account, &event);
+}
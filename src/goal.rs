@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// What an account does once its goal is reached.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalAction {
+    /// keep claiming and harvesting, but no more clicks
+    StopClick,
+    /// only claim and harvest: no clicks, quests or speedups
+    HarvestOnly,
+    /// sell repeated bananas, then keep going as usual
+    Sell,
+    /// stop the account entirely
+    Pause,
+}
+
+/// Per-account target, reached as soon as any of the configured targets is met.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Goal {
+    pub peel: Option<f64>,
+    pub usdt: Option<f64>,
+    pub banana_id: Option<i64>,
+    pub action: GoalAction,
+}
+
+pub struct Progress<'a> {
+    goal: &'a Goal,
+    peel: f64,
+    usdt: f64,
+    has_banana: bool,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new(goal: &'a Goal, peel: f64, usdt: f64, banana_ids: &[i64]) -> Self {
+        Self {
+            goal,
+            peel,
+            usdt,
+            has_banana: goal.banana_id.is_some_and(|id| banana_ids.contains(&id)),
+        }
+    }
+
+    pub fn reached(&self) -> bool {
+        self.goal.peel.is_some_and(|peel| self.peel >= peel)
+            || self.goal.usdt.is_some_and(|usdt| self.usdt >= usdt)
+            || self.has_banana
+    }
+}
+
+fn percent(current: f64, target: f64) -> f64 {
+    if target <= 0.0 {
+        return 100.0;
+    }
+    (current / target * 100.0).min(100.0)
+}
+
+impl Display for Progress<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut parts = vec![];
+        if let Some(peel) = self.goal.peel {
+            parts.push(format!(
+                "peel {}/{} ({:.1}%)",
+                self.peel,
+                peel,
+                percent(self.peel, peel)
+            ));
+        }
+        if let Some(usdt) = self.goal.usdt {
+            parts.push(format!(
+                "usdt {}/{} ({:.1}%)",
+                self.usdt,
+                usdt,
+                percent(self.usdt, usdt)
+            ));
+        }
+        if let Some(banana_id) = self.goal.banana_id {
+            parts.push(format!(
+                "banana #{} {}",
+                banana_id,
+                if self.has_banana { "owned" } else { "missing" }
+            ));
+        }
+        if parts.is_empty() {
+            parts.push("no target".to_string());
+        }
+        write!(f, "{}", parts.join(", "))?;
+        if self.reached() {
+            write!(f, " -> reached, {:?}", self.goal.action)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(peel: Option<f64>, usdt: Option<f64>, banana_id: Option<i64>) -> Goal {
+        Goal {
+            peel,
+            usdt,
+            banana_id,
+            action: GoalAction::Pause,
+        }
+    }
+
+    #[test]
+    fn reached_by_any_target() {
+        let goal = goal(Some(100.0), Some(1.0), Some(7));
+        assert!(!Progress::new(&goal, 99.0, 0.5, &[1, 2]).reached());
+        assert!(Progress::new(&goal, 100.0, 0.0, &[]).reached());
+        assert!(Progress::new(&goal, 0.0, 1.5, &[]).reached());
+        assert!(Progress::new(&goal, 0.0, 0.0, &[7]).reached());
+    }
+
+    #[test]
+    fn no_target_is_never_reached() {
+        let goal = goal(None, None, None);
+        assert!(!Progress::new(&goal, 1e9, 1e9, &[1]).reached());
+        assert_eq!(Progress::new(&goal, 0.0, 0.0, &[]).to_string(), "no target");
+    }
+
+    #[test]
+    fn display_shows_every_target() {
+        let goal = goal(Some(200.0), Some(2.0), Some(7));
+        assert_eq!(
+            Progress::new(&goal, 50.0, 0.5, &[]).to_string(),
+            "peel 50/200 (25.0%), usdt 0.5/2 (25.0%), banana #7 missing"
+        );
+        assert_eq!(
+            Progress::new(&goal, 300.0, 0.5, &[7]).to_string(),
+            "peel 300/200 (100.0%), usdt 0.5/2 (25.0%), banana #7 owned -> reached, Pause"
+        );
+    }
+}
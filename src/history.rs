@@ -0,0 +1,162 @@
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const WEEK_MS: i64 = 7 * DAY_MS;
+
+/// One point of an account's balance history.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Snapshot {
+    pub timestamp: i64,
+    pub peel: f64,
+    pub usdt: f64,
+}
+
+/// Append a snapshot to `{dir}/{name}.jsonl`.
+pub fn record(dir: &Path, name: &str, snapshot: &Snapshot) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{}.jsonl", name)))?;
+    let line = serde_json::to_string(snapshot).expect("Unable to serialize snapshot");
+    writeln!(file, "{}", line)
+}
+
+pub fn load(dir: &Path, name: &str) -> Vec<Snapshot> {
+    let file = match fs::File::open(dir.join(format!("{}.jsonl", name))) {
+        Ok(file) => file,
+        Err(_) => return vec![],
+    };
+    let mut snapshots: Vec<Snapshot> = std::io::BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect();
+    snapshots.sort_by_key(|s| s.timestamp);
+    snapshots
+}
+
+pub fn load_all(dir: &Path) -> BTreeMap<String, Vec<Snapshot>> {
+    let mut all = BTreeMap::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return all,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+            all.insert(name.to_string(), load(dir, name));
+        }
+    }
+    all
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct Gain {
+    pub peel: f64,
+    pub usdt: f64,
+}
+
+/// Growth between the latest snapshot and the last one taken at least `window_ms` earlier.
+/// Falls back to the oldest snapshot when the history is shorter than the window.
+pub fn gain(snapshots: &[Snapshot], window_ms: i64) -> Gain {
+    let latest = match snapshots.last() {
+        Some(latest) => latest,
+        None => return Gain::default(),
+    };
+    let since = latest.timestamp - window_ms;
+    let base = snapshots
+        .iter()
+        .rev()
+        .find(|s| s.timestamp <= since)
+        .unwrap_or(&snapshots[0]);
+
+    Gain {
+        peel: latest.peel - base.peel,
+        usdt: latest.usdt - base.usdt,
+    }
+}
+
+/// An account is stalled when its latest snapshot is more than a day old, or when it has
+/// at least a day of history and nothing was earned during the last day.
+pub fn is_stalled(snapshots: &[Snapshot], now: i64) -> bool {
+    let (first, latest) = match (snapshots.first(), snapshots.last()) {
+        (Some(first), Some(latest)) => (first, latest),
+        _ => return true,
+    };
+    if now - latest.timestamp > DAY_MS {
+        return true;
+    }
+    let day = gain(snapshots, DAY_MS);
+    latest.timestamp - first.timestamp >= DAY_MS && day.peel <= 0.0 && day.usdt <= 0.0
+}
+
+/// Print the balance report, only for `accounts` unless it is empty.
+pub fn print_report(dir: &Path, accounts: &[String]) {
+    let mut all = load_all(dir);
+    if !accounts.is_empty() {
+        all.retain(|name, _| accounts.contains(name));
+    }
+    if all.is_empty() {
+        println!("no balance history found in {:?}", dir);
+        return;
+    }
+
+    let now = crate::utils::get_current_timestamp();
+    let mut total = (0f64, 0f64, Gain::default(), Gain::default());
+    let mut stalled = vec![];
+
+    println!(
+        "{:<20} {:>12} {:>10} {:>12} {:>10} {:>12} {:>10}  last snapshot",
+        "account", "peel", "usdt", "peel/24h", "usdt/24h", "peel/7d", "usdt/7d"
+    );
+    for (name, snapshots) in &all {
+        let latest = match snapshots.last() {
+            Some(latest) => latest,
+            None => continue,
+        };
+        let day = gain(snapshots, DAY_MS);
+        let week = gain(snapshots, WEEK_MS);
+        println!(
+            "{:<20} {:>12.2} {:>10.4} {:>+12.2} {:>+10.4} {:>+12.2} {:>+10.4}  {}",
+            name,
+            latest.peel,
+            latest.usdt,
+            day.peel,
+            day.usdt,
+            week.peel,
+            week.usdt,
+            Local
+                .timestamp_millis_opt(latest.timestamp)
+                .single()
+                .map(|t| t.format("%F %T").to_string())
+                .unwrap_or_default()
+        );
+
+        total.0 += latest.peel;
+        total.1 += latest.usdt;
+        total.2.peel += day.peel;
+        total.2.usdt += day.usdt;
+        total.3.peel += week.peel;
+        total.3.usdt += week.usdt;
+        if is_stalled(snapshots, now) {
+            stalled.push(name.as_str());
+        }
+    }
+    println!(
+        "{:<20} {:>12.2} {:>10.4} {:>+12.2} {:>+10.4} {:>+12.2} {:>+10.4}",
+        "total", total.0, total.1, total.2.peel, total.2.usdt, total.3.peel, total.3.usdt
+    );
+
+    if !stalled.is_empty() {
+        println!("\nstalled accounts: {}", stalled.join(", "));
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+/// A minimal HTTP/1.1 server for the local endpoints (metrics, dashboard, control).
+/// Every connection serves exactly one request.
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// names in lowercase
+    pub headers: HashMap<String, String>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    pub fn json(status: u16, body: &serde_json::Value) -> Self {
+        Self::new(status, "application/json", body.to_string())
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(404, "text/plain", "not found")
+    }
+}
+
+pub type Handler =
+    Arc<dyn Fn(Request) -> Pin<Box<dyn Future<Output = Response> + Send>> + Send + Sync>;
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        _ => "Internal Server Error",
+    }
+}
+
+async fn read_request(stream: &mut tokio::net::TcpStream) -> std::io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or("/").to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+
+    let path = match target.split_once('?') {
+        Some((path, _)) => path.to_string(),
+        None => target,
+    };
+
+    Ok(Request {
+        method,
+        path,
+        headers,
+    })
+}
+
+/// Serve `handler` on `addr` until the process exits.
+pub async fn serve(addr: SocketAddr, handler: Handler) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            let response = match read_request(&mut stream).await {
+                Ok(request) => handler(request).await,
+                Err(_) => Response::new(400, "text/plain", "bad request"),
+            };
+            let head = format!(
+                "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                response.status,
+                reason(response.status),
+                response.content_type,
+                response.body.len()
+            );
+            let _ = stream.write_all(head.as_bytes()).await;
+            let _ = stream.write_all(response.body.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        });
+    }
+}
+
+/// Spawn [`serve`] in the background, logging bind errors under `name`.
+pub fn spawn(name: &'static str, addr: SocketAddr, handler: Handler) {
+    tokio::spawn(async move {
+        log::info!("{} listening on http://{}", name, addr);
+        if let Err(err) = serve(addr, handler).await {
+            log::error!("{} on {} err: {:?}", name, addr, err);
+        }
+    });
+}
@@ -0,0 +1,328 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::Url;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// Page of the web app, completed with a `#tgWebAppData=...` fragment when only the initData
+/// was pasted.
+const WEB_APP_URL: &str = "https://banana.carv.io/";
+
+/// What `encodeURIComponent` escapes, as Telegram does in the links.
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+/// The Telegram user a web app was opened by.
+#[allow(dead_code)]
+#[derive(Deserialize, Clone, Debug)]
+pub struct WebAppUser {
+    pub id: i64,
+    #[serde(default)]
+    pub first_name: String,
+    pub last_name: Option<String>,
+    pub username: Option<String>,
+    pub language_code: Option<String>,
+}
+
+impl Display for WebAppUser {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.username {
+            Some(username) => write!(f, "@{} ({})", username, self.id),
+            None => write!(f, "{} ({})", self.first_name, self.id),
+        }
+    }
+}
+
+/// The `tgWebAppData` Telegram passes to a web app, sent as is to log in.
+#[allow(dead_code)]
+#[derive(Clone, Debug)]
+pub struct InitData {
+    /// the decoded query string
+    pub raw: String,
+    pub query_id: Option<String>,
+    pub user: Option<WebAppUser>,
+    /// unix seconds
+    pub auth_date: Option<i64>,
+    pub hash: Option<String>,
+    pub start_param: Option<String>,
+}
+
+impl InitData {
+    /// Fields Telegram always sends that are missing.
+    pub fn missing(&self) -> Vec<&'static str> {
+        let mut missing = vec![];
+        if self.user.is_none() {
+            missing.push("user");
+        }
+        if self.auth_date.is_none() {
+            missing.push("auth_date");
+        }
+        if self.hash.is_none() {
+            missing.push("hash");
+        }
+        missing
+    }
+}
+
+/// A Telegram web app link: `https://...#tgWebAppData=...&tgWebAppVersion=...`.
+#[derive(Clone, Debug)]
+pub struct WebAppLink {
+    pub init_data: InitData,
+    pub version: Option<String>,
+    pub platform: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum LinkError {
+    Url(String),
+    NoFragment,
+    NoInitData,
+    /// a `t.me` link, it only holds the referral code
+    Invite,
+    Invalid(String),
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkError::Url(err) => write!(f, "link is not a url: {}", err),
+            LinkError::NoFragment => write!(
+                f,
+                "link has no #tgWebAppData=... part, copy the whole url of the web app"
+            ),
+            LinkError::NoInitData => write!(f, "link has no tgWebAppData in its #fragment"),
+            LinkError::Invite => write!(
+                f,
+                "a t.me link can't log in, open it in Telegram Web and copy the url of the web app"
+            ),
+            LinkError::Invalid(err) => write!(f, "tgWebAppData is invalid: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+fn query(query: &str) -> Result<HashMap<String, String>, LinkError> {
+    serde_urlencoded::from_str(query).map_err(|err| LinkError::Invalid(err.to_string()))
+}
+
+/// Parse `tgWebAppData`, already decoded from the link.
+pub fn parse_init_data(raw: &str) -> Result<InitData, LinkError> {
+    let mut fields = query(raw)?;
+    let user = match fields.remove("user") {
+        Some(user) => Some(
+            serde_json::from_str(&user)
+                .map_err(|err| LinkError::Invalid(format!("user: {}", err)))?,
+        ),
+        None => None,
+    };
+    let auth_date = match fields.remove("auth_date") {
+        Some(date) => Some(
+            date.parse()
+                .map_err(|_| LinkError::Invalid("auth_date is not a timestamp".into()))?,
+        ),
+        None => None,
+    };
+    Ok(InitData {
+        raw: raw.to_string(),
+        query_id: fields.remove("query_id"),
+        user,
+        auth_date,
+        hash: fields.remove("hash"),
+        start_param: fields.remove("start_param"),
+    })
+}
+
+/// The web app link of what users paste: the web app url (iframe src), the `tgWebAppData`
+/// value as found in it, or the decoded initData query string.
+pub fn normalize(input: &str) -> Result<String, LinkError> {
+    let input = input.trim().trim_matches(['"', '\'']);
+    if let Ok(url) = Url::parse(input) {
+        if matches!(url.host_str(), Some("t.me" | "telegram.me")) {
+            return Err(LinkError::Invite);
+        }
+        return Ok(input.to_string());
+    }
+
+    let data = input.trim_start_matches('#');
+    if data.starts_with("tgWebAppData=") {
+        return Ok(format!("{}#{}", WEB_APP_URL, data));
+    }
+    // a decoded query string has its `=`, the value from the link has them encoded
+    let data = match data.contains('=') {
+        true => utf8_percent_encode(data, COMPONENT).to_string(),
+        false => data.to_string(),
+    };
+    Ok(format!("{}#tgWebAppData={}", WEB_APP_URL, data))
+}
+
+/// The referral code of a `startapp` parameter, e.g. `HHQJ6T4` for `referral=HHQJ6T4`.
+pub fn referral_code(start_param: &str) -> String {
+    start_param
+        .strip_prefix("referral=")
+        .unwrap_or(start_param)
+        .to_string()
+}
+
+/// The invite code of what users pass as one: a link with a `startapp` or start param, or
+/// the code itself.
+pub fn invite_code(input: &str) -> Option<String> {
+    let input = input.trim();
+    let url = match Url::parse(input) {
+        Ok(url) => url,
+        Err(_) => return Some(input.to_string()).filter(|code| !code.is_empty()),
+    };
+    match url.query_pairs().find(|(key, _)| key == "startapp") {
+        Some((_, start_param)) => Some(referral_code(&start_param)),
+        None => parse(input)
+            .ok()?
+            .init_data
+            .start_param
+            .as_deref()
+            .map(referral_code),
+    }
+}
+
+/// Parse a web app link in any form [`normalize`] accepts, whatever the order of its
+/// parameters.
+pub fn parse(link: &str) -> Result<WebAppLink, LinkError> {
+    let link = normalize(link)?;
+    let url = Url::parse(&link).map_err(|err| LinkError::Url(err.to_string()))?;
+    let mut params = query(url.fragment().ok_or(LinkError::NoFragment)?)?;
+    let mut init_data =
+        parse_init_data(&params.remove("tgWebAppData").ok_or(LinkError::NoInitData)?)?;
+    if init_data.start_param.is_none() {
+        init_data.start_param = url
+            .query_pairs()
+            .find(|(key, _)| key == "tgWebAppStartParam")
+            .map(|(_, value)| value.into_owned());
+    }
+    Ok(WebAppLink {
+        init_data,
+        version: params.remove("tgWebAppVersion"),
+        platform: params.remove("tgWebAppPlatform"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// initData as Telegram decodes it, `start_param` is `referral=HHQJ6T4`.
+    const DECODED: &str = "query_id=AAH&user=%7B%22id%22%3A42%2C%22first_name%22%3A%22Ann%22%2C%22username%22%3A%22ann%22%7D&auth_date=1721000000&start_param=referral%3DHHQJ6T4&hash=abc";
+
+    fn encoded() -> String {
+        utf8_percent_encode(DECODED, COMPONENT).to_string()
+    }
+
+    fn url() -> String {
+        format!(
+            "https://banana.carv.io/?tgWebAppStartParam=referral%3DHHQJ6T4#tgWebAppData={}&tgWebAppVersion=7.6&tgWebAppPlatform=weba",
+            encoded()
+        )
+    }
+
+    fn assert_parsed(link: &WebAppLink) {
+        let init_data = &link.init_data;
+        assert_eq!(init_data.raw, DECODED);
+        assert_eq!(init_data.user.as_ref().map(|user| user.id), Some(42));
+        assert_eq!(init_data.auth_date, Some(1721000000));
+        assert_eq!(init_data.hash.as_deref(), Some("abc"));
+        assert_eq!(init_data.start_param.as_deref(), Some("referral=HHQJ6T4"));
+        assert!(init_data.missing().is_empty());
+    }
+
+    #[test]
+    fn parses_every_pasted_form() {
+        let forms = [
+            url(),
+            format!("  \"{}\"  ", url()),
+            format!("tgWebAppData={}", encoded()),
+            format!("#tgWebAppData={}&tgWebAppVersion=7.6", encoded()),
+            encoded(),
+            DECODED.to_string(),
+        ];
+        for form in forms {
+            let link = parse(&form).unwrap_or_else(|err| panic!("{}: {}", form, err));
+            assert_parsed(&link);
+        }
+        let link = parse(&url()).unwrap();
+        assert_eq!(link.version.as_deref(), Some("7.6"));
+        assert_eq!(link.platform.as_deref(), Some("weba"));
+    }
+
+    #[test]
+    fn normalize_completes_the_web_app_url() {
+        assert_eq!(normalize(&url()).unwrap(), url());
+        let expected = format!("{}#tgWebAppData={}", WEB_APP_URL, encoded());
+        assert_eq!(normalize(&encoded()).unwrap(), expected);
+        assert_eq!(normalize(DECODED).unwrap(), expected);
+        assert_eq!(
+            normalize(&format!("tgWebAppData={}", encoded())).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn refuses_links_that_cant_log_in() {
+        let invite = "https://t.me/OfficialBananaBot/banana?startapp=referral=HHQJ6T4";
+        assert!(matches!(normalize(invite), Err(LinkError::Invite)));
+        assert!(matches!(parse(invite), Err(LinkError::Invite)));
+        assert!(matches!(
+            parse("https://banana.carv.io/"),
+            Err(LinkError::NoFragment)
+        ));
+        assert!(matches!(
+            parse("https://banana.carv.io/#tgWebAppVersion=7.6"),
+            Err(LinkError::NoInitData)
+        ));
+        assert!(matches!(
+            parse("auth_date=yesterday&hash=abc"),
+            Err(LinkError::Invalid(_))
+        ));
+
+        let link = parse("query_id=AAH&hash=abc").unwrap();
+        assert_eq!(link.init_data.missing(), ["user", "auth_date"]);
+    }
+
+    #[test]
+    fn start_param_falls_back_to_the_url() {
+        let link = format!(
+            "https://banana.carv.io/?tgWebAppStartParam=referral%3DXYZ#tgWebAppData={}",
+            utf8_percent_encode("user=%7B%22id%22%3A1%7D&auth_date=1&hash=h", COMPONENT)
+        );
+        let link = parse(&link).unwrap();
+        assert_eq!(link.init_data.start_param.as_deref(), Some("referral=XYZ"));
+    }
+
+    #[test]
+    fn referral_code_strips_the_prefix() {
+        assert_eq!(referral_code("referral=HHQJ6T4"), "HHQJ6T4");
+        assert_eq!(referral_code("HHQJ6T4"), "HHQJ6T4");
+    }
+
+    #[test]
+    fn invite_code_of_every_form() {
+        assert_eq!(invite_code(" HHQJ6T4 ").as_deref(), Some("HHQJ6T4"));
+        assert_eq!(invite_code(""), None);
+        assert_eq!(
+            invite_code("https://t.me/OfficialBananaBot/banana?startapp=referral=HHQJ6T4")
+                .as_deref(),
+            Some("HHQJ6T4")
+        );
+        assert_eq!(
+            invite_code("https://t.me/OfficialBananaBot/banana?startapp=HHQJ6T4").as_deref(),
+            Some("HHQJ6T4")
+        );
+        assert_eq!(invite_code(&url()).as_deref(), Some("HHQJ6T4"));
+        assert_eq!(invite_code("https://t.me/OfficialBananaBot/banana"), None);
+    }
+}
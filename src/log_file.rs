@@ -0,0 +1,115 @@
+use chrono::{Local, NaiveDate};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+const COMBINED: &str = "combined";
+
+/// A log file rotated when it grows past `max_size` or when the day changes.
+/// Rotated files are kept as `{name}.log.1` (newest) up to `{name}.log.{keep}`.
+struct RotatingFile {
+    path: PathBuf,
+    file: fs::File,
+    size: u64,
+    opened: NaiveDate,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let metadata = file.metadata()?;
+        let opened = metadata
+            .modified()
+            .map(|t| chrono::DateTime::<Local>::from(t).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+
+        Ok(Self {
+            path,
+            file,
+            size: metadata.len(),
+            opened,
+        })
+    }
+
+    fn rotate(&mut self, keep: usize) -> std::io::Result<()> {
+        let rotated = |n: usize| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        if keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated(keep));
+            for n in (1..keep).rev() {
+                let _ = fs::rename(rotated(n), rotated(n + 1));
+            }
+            fs::rename(&self.path, rotated(1))?;
+        }
+        *self = Self::open(self.path.clone())?;
+        self.opened = Local::now().date_naive();
+
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str, max_size: u64, keep: usize) -> std::io::Result<()> {
+        let today = Local::now().date_naive();
+        if self.size > 0 && (self.size + line.len() as u64 > max_size || self.opened != today) {
+            self.rotate(keep)?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+
+        Ok(())
+    }
+}
+
+struct FileSink {
+    dir: PathBuf,
+    max_size: u64,
+    keep: usize,
+    files: Mutex<HashMap<String, RotatingFile>>,
+}
+
+static SINK: OnceLock<FileSink> = OnceLock::new();
+
+/// Start writing one log file per account plus a combined one into `dir`.
+pub fn init(dir: &Path, max_size: u64, keep: usize) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let _ = SINK.set(FileSink {
+        dir: dir.to_path_buf(),
+        max_size,
+        keep,
+        files: Mutex::new(HashMap::new()),
+    });
+
+    Ok(())
+}
+
+/// Append `line` to the account's file and to the combined file; a no-op unless [`init`] was called.
+pub fn write(account: &str, line: &str) {
+    let sink = match SINK.get() {
+        Some(sink) => sink,
+        None => return,
+    };
+    let mut files = sink.files.lock().unwrap();
+    for name in [account, COMBINED] {
+        if !files.contains_key(name) {
+            match RotatingFile::open(sink.dir.join(format!("{}.log", name))) {
+                Ok(file) => {
+                    files.insert(name.to_string(), file);
+                }
+                Err(err) => {
+                    eprintln!("open log file for {} err: {:?}", name, err);
+                    continue;
+                }
+            }
+        }
+        if let Some(file) = files.get_mut(name) {
+            if let Err(err) = file.write_line(line, sink.max_size, sink.keep) {
+                eprintln!("write log file for {} err: {:?}", name, err);
+            }
+        }
+    }
+}
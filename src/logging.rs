@@ -0,0 +1,178 @@
+use crate::log_file;
+use chrono::Local;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// colored, human readable lines (default)
+    #[default]
+    Human,
+    /// one JSON object per event
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "human" | "text" => Ok(LogFormat::Human),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format: {}", s)),
+        }
+    }
+}
+
+/// Outcome of one API call.
+pub struct ApiEvent<'a> {
+    pub account: &'a str,
+    pub action: &'a str,
+    pub endpoint: &'a str,
+    pub status: Option<u16>,
+    pub code: Option<i64>,
+    pub duration_ms: u128,
+    pub ok: bool,
+}
+
+// lines kept in memory for the TUI log pane
+const RECENT_CAPACITY: usize = 2000;
+
+static RECENT: LazyLock<Mutex<VecDeque<(String, String)>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::with_capacity(RECENT_CAPACITY)));
+
+struct JsonLogger;
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        println!(
+            "{}",
+            json!({
+                "ts": Local::now().to_rfc3339(),
+                "level": record.level().as_str().to_lowercase(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            })
+        );
+    }
+
+    fn flush(&self) {}
+}
+
+fn format() -> LogFormat {
+    crate::settings::get().log_format
+}
+
+fn tui() -> bool {
+    crate::settings::get().tui
+}
+
+fn remember(account: &str, line: String) {
+    let mut recent = RECENT.lock().unwrap();
+    if recent.len() == RECENT_CAPACITY {
+        recent.pop_front();
+    }
+    recent.push_back((account.to_string(), line));
+}
+
+/// The last `n` lines logged for `account`, oldest first.
+pub fn recent(account: &str, n: usize) -> Vec<String> {
+    let recent = RECENT.lock().unwrap();
+    let mut lines: Vec<String> = recent
+        .iter()
+        .rev()
+        .filter(|(a, _)| a == account)
+        .take(n)
+        .map(|(_, line)| line.clone())
+        .collect();
+    lines.reverse();
+    lines
+}
+
+/// Install the logger for `format`; in TUI mode nothing is printed to stdout.
+pub fn init(format: LogFormat) {
+    if tui() {
+        return;
+    }
+    match format {
+        LogFormat::Human => colog::init(),
+        LogFormat::Json => {
+            log::set_boxed_logger(Box::new(JsonLogger)).expect("logger already initialized");
+            log::set_max_level(LevelFilter::Info);
+        }
+    }
+}
+
+/// A free-form message about one account.
+pub fn message(level: Level, account: &str, msg: &str) {
+    let line = match format() {
+        LogFormat::Human => {
+            let now = crate::utils::now();
+            if !tui() {
+                log::log!(level, "[{}] [{}]: {}", now, account, msg);
+            }
+            format!("[{}] [{}] [{}]: {}", now, level, account, msg)
+        }
+        LogFormat::Json => {
+            let line = json!({
+                "ts": Local::now().to_rfc3339(),
+                "level": level.as_str().to_lowercase(),
+                "account": account,
+                "message": msg,
+            })
+            .to_string();
+            if !tui() {
+                println!("{}", line);
+            }
+            line
+        }
+    };
+    log_file::write(account, &line);
+    if level == Level::Info {
+        crate::state::set_last_action(account, msg);
+    }
+    remember(account, line);
+}
+
+pub fn api_event(event: &ApiEvent) {
+    match format() {
+        LogFormat::Human => log::debug!(
+            "[{}] [{}]: {} {} status={:?} code={:?} {}ms",
+            crate::utils::now(),
+            event.account,
+            event.action,
+            event.endpoint,
+            event.status,
+            event.code,
+            event.duration_ms
+        ),
+        LogFormat::Json => {
+            let line = json!({
+                "ts": Local::now().to_rfc3339(),
+                "level": if event.ok { "info" } else { "error" },
+                "account": event.account,
+                "action": event.action,
+                "endpoint": event.endpoint,
+                "status": event.status,
+                "code": event.code,
+                "duration_ms": event.duration_ms,
+                "result": if event.ok { "ok" } else { "error" },
+            })
+            .to_string();
+            if !tui() {
+                println!("{}", line);
+            }
+            log_file::write(event.account, &line);
+        }
+    }
+}
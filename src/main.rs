@@ -1,22 +1,56 @@
-use colog;
+use clap::Parser;
 use colored::*;
 use log::info;
 use rand::Rng;
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, COOKIE};
-use reqwest::{StatusCode, Url};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
-use std::path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
 use std::time::Duration;
+use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
+mod api;
+mod cli;
+mod config;
+mod control;
+mod cookies;
+mod dashboard;
+mod events;
+mod goal;
+mod history;
+mod http;
+mod init_data;
+mod log_file;
+mod logging;
+mod metrics;
+mod notify;
+mod secrets;
+mod settings;
+mod state;
+mod summary;
+mod telegram;
+mod token;
+mod tui;
 mod utils;
+mod validate;
+mod webhook;
 
+use events::Event;
+use goal::{Goal, GoalAction};
+use state::Command;
 use utils::User;
 
+/// How many times `login` and `get_user_info` are tried before the account is given up,
+/// without limit unless set. `once` sets it so that a cron job never hangs on one account.
+static ATTEMPTS: OnceLock<u32> = OnceLock::new();
+const ONCE_ATTEMPTS: u32 = 3;
+
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Debug)]
 struct TapData {
     number_gem: f32,
@@ -27,9 +61,12 @@ struct TapData {
     number_tap: i64,
 }
 
+#[allow(clippy::enum_variant_names, dead_code)]
 #[derive(Debug)]
 enum BananaErr {
     LoginErr,
+    /// the login was refused with a 400, 401 or 403, the link is no good anymore
+    LinkRejected,
     GetUserInfoErr,
     QuestListErr,
 }
@@ -44,8 +81,10 @@ impl std::error::Error for BananaErr {}
 
 struct Banana {
     name: String,
-    access_token: String,
-    cookie_token: String,
+    /// replaced when the account logs in again before it expires
+    access_token: RwLock<String>,
+    client: reqwest::Client,
+    features: utils::Features,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -62,64 +101,136 @@ struct BananaUserInfo {
     lottery_info: LotteryInfo,
     max_click_count: i32,
     today_click_count: i32,
+    #[serde(default)]
+    peel: f64,
+    #[serde(default)]
+    usdt: f64,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+struct BananaItem {
+    banana_id: i64,
+    name: String,
+    count: i32,
+    #[serde(default)]
+    ripeness: String,
+}
+
+fn next_claim_in(lottery_info: &LotteryInfo) -> i64 {
+    lottery_info.last_countdown_start_time + (lottery_info.countdown_interval as i64 * 60000)
+        - utils::get_current_timestamp()
+}
+
+// how often the balance of every account is written to the history
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// wait before trying to refresh the access token again
+const REFRESH_RETRY: Duration = Duration::from_secs(5 * 60);
+
+fn record_snapshot(dir: &Path, name: &str, userinfo: &BananaUserInfo) {
+    let snapshot = history::Snapshot {
+        timestamp: utils::get_current_timestamp(),
+        peel: userinfo.peel,
+        usdt: userinfo.usdt,
+    };
+    if let Err(err) = history::record(dir, name, &snapshot) {
+        utils::format_error(name, &format!("record snapshot err: {:?}", err));
+    }
 }
 
 impl Banana {
-    fn new(name: String, access_token: String, cookie_token: String) -> Self {
-        Self {
+    fn new(
+        name: String,
+        access_token: String,
+        cookie_token: String,
+        proxy: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        cookies::seed(&name, &api::base_url(), &cookie_token);
+        Ok(Self {
             name,
-            access_token,
-            cookie_token,
-        }
+            access_token: RwLock::new(access_token),
+            client: api::client(proxy)?,
+            features: utils::Features::default(),
+        })
     }
 
     fn request(&self) -> (reqwest::Client, HeaderMap) {
-        let client = reqwest::Client::new();
+        let client = self.client.clone();
         let mut headers = HeaderMap::new();
         utils::init_headers(&mut headers);
-        headers.insert(COOKIE, HeaderValue::from_str(&self.cookie_token).unwrap());
+        // cookies come from the account's jar, see `api::send`
         headers.insert(
             AUTHORIZATION,
-            HeaderValue::from_str(&format!("Bearer {}", &self.access_token)).unwrap(),
+            HeaderValue::from_str(&format!("Bearer {}", self.access_token.read().unwrap()))
+                .unwrap(),
         );
 
         (client, headers)
     }
 
+    fn set_access_token(&self, access_token: String) {
+        *self.access_token.write().unwrap() = access_token;
+    }
+
+    /// Claims of the access token, `None` when it isn't a JWT.
+    fn token_claims(&self) -> Option<token::Claims> {
+        token::claims(&self.access_token.read().unwrap())
+    }
+
     async fn get_user_info(&self) -> Result<BananaUserInfo, Box<dyn std::error::Error>> {
         let (client, headers) = self.request();
+        let mut attempts = 0;
 
         loop {
-            let response = client
-                .get("https://interface.carv.io/banana/get_user_info")
-                .headers(headers.clone())
-                .send()
-                .await?;
+            attempts += 1;
+            let response = api::send(
+                &self.name,
+                "user_info",
+                client
+                    .get(format!("{}get_user_info", api::API_BASE))
+                    .headers(headers.clone()),
+            )
+            .await?;
 
             let status = response.status();
             if status == StatusCode::OK {
-                let bui: serde_json::Value = response.json().await.map_err(|err| {
+                let bui: serde_json::Value = response.json().map_err(|err| {
                     utils::format_error(&self.name, &format!("err: {:?}", err));
                     Box::new(BananaErr::GetUserInfoErr)
                 })?;
                 if bui["code"] == 0 {
                     let bui: BananaUserInfo = serde_json::from_value(bui["data"].clone()).unwrap();
+                    events::emit(&self.name, Event::UserInfo(&bui));
                     return Ok(bui);
                 }
-            } else if status == StatusCode::TOO_MANY_REQUESTS {
-                // Handle 429 Too Many Requests
-                if let Some(retry_after) = response.headers().get("Retry-After") {
-                    if let Ok(retry_after) = retry_after.to_str() {
-                        if let Ok(retry_after) = retry_after.parse::<u64>() {
-                            println!("Rate limited. Retrying after {} seconds...", retry_after);
-                            sleep(Duration::from_secs(retry_after)).await;
-                            continue;
-                        }
-                    }
-                }
+            } else if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+                let msg = format!("get_user_info rejected the tokens: {:?}", status);
+                utils::format_error(&self.name, &msg);
+                notify::send(&self.name, notify::Kind::AuthFailure, &msg);
+                return Err(Box::new(BananaErr::GetUserInfoErr));
+            }
+            if ATTEMPTS.get().is_some_and(|max| attempts >= *max) {
+                utils::format_error(
+                    &self.name,
+                    &format!("get_user_info failed {} times, giving up", attempts),
+                );
+                return Err(Box::new(BananaErr::GetUserInfoErr));
             }
-            println!("Rate limited. Retrying after 60 seconds...");
-            sleep(Duration::from_secs(60)).await;
+            // Handle 429 Too Many Requests
+            let retry_after = match status {
+                StatusCode::TOO_MANY_REQUESTS => response.retry_after().unwrap_or(60),
+                _ => 60,
+            };
+            utils::format_println(
+                &self.name,
+                &format!("Rate limited. Retrying after {} seconds...", retry_after),
+            );
+            events::emit(
+                &self.name,
+                Event::Retry {
+                    endpoint: "get_user_info",
+                },
+            );
+            sleep(Duration::from_secs(retry_after)).await;
         }
     }
 
@@ -131,6 +242,7 @@ impl Banana {
         let (client, headers) = self.request();
 
         let mut rest_count = max_click_count - today_click_count;
+        events::emit(&self.name, Event::ClickLimit(max_click_count));
 
         loop {
             if rest_count <= 0 {
@@ -139,30 +251,34 @@ impl Banana {
             }
 
             let mut rand_num = rest_count;
-            let mut rng = rand::thread_rng();
             if rand_num > 10 {
-                rand_num = rng.gen_range(10..rand_num);
+                rand_num = rand::thread_rng().gen_range(10..rand_num);
             }
 
-            let response = client
-                .post("https://interface.carv.io/banana/do_click")
-                .headers(headers.clone())
-                .body(
-                    json!({
-                        "clickCount": rand_num,
-                    })
-                    .to_string(),
-                )
-                .send()
-                .await?;
+            let response = api::send(
+                &self.name,
+                "click",
+                client
+                    .post(format!("{}do_click", api::API_BASE))
+                    .headers(headers.clone())
+                    .body(
+                        json!({
+                            "clickCount": rand_num,
+                        })
+                        .to_string(),
+                    ),
+            )
+            .await?;
 
             if response.status() == StatusCode::OK {
-                let s: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+                let s: serde_json::Value = response.json()?;
                 if s["code"] == 0 {
                     rest_count -= rand_num;
+                    events::emit(&self.name, Event::Clicked(rand_num));
                 }
                 utils::format_println(&self.name, &format!("click: {:?}", s));
-                sleep(Duration::from_millis(rng.gen_range(500..3000))).await;
+                let delay = rand::thread_rng().gen_range(500..3000);
+                sleep(Duration::from_millis(delay)).await;
             }
         }
 
@@ -176,19 +292,25 @@ impl Banana {
 
         let (client, headers) = self.request();
 
-        let response = client
-            .post("https://interface.carv.io/banana/claim_lottery")
-            .headers(headers)
-            .body(
-                json!({
-                    "claimLotteryType": 1
-                })
-                .to_string(),
-            )
-            .send()
-            .await?;
+        let response = api::send(
+            &self.name,
+            "claim",
+            client
+                .post(format!("{}claim_lottery", api::API_BASE))
+                .headers(headers)
+                .body(
+                    json!({
+                        "claimLotteryType": 1
+                    })
+                    .to_string(),
+                ),
+        )
+        .await?;
 
         utils::format_println(&self.name, &format!("claim done!: {:?}", response.status()));
+        if response.status() == StatusCode::OK {
+            events::emit(&self.name, Event::Claimed);
+        }
 
         Ok(())
     }
@@ -204,12 +326,15 @@ impl Banana {
         let mut cnt = userinfo.lottery_info.remain_lottery_count;
 
         while cnt > 0 {
-            let response = client
-                .post("https://interface.carv.io/banana/do_lottery")
-                .headers(headers.clone())
-                .body("{}")
-                .send()
-                .await?;
+            let response = api::send(
+                &self.name,
+                "harvest",
+                client
+                    .post(format!("{}do_lottery", api::API_BASE))
+                    .headers(headers.clone())
+                    .body("{}"),
+            )
+            .await?;
 
             let status = response.status();
             utils::format_println(&self.name, &format!("do_lottery: {:?}", status));
@@ -218,7 +343,7 @@ impl Banana {
                 break;
             }
 
-            let result = response.json::<serde_json::Value>().await?;
+            let result = response.json::<serde_json::Value>()?;
             if result["code"].as_i64().unwrap() != 0 {
                 break;
             }
@@ -232,10 +357,19 @@ impl Banana {
                     result["data"]["ripeness"].as_str().unwrap()
                 ),
             );
+            events::emit(
+                &self.name,
+                Event::Harvested {
+                    name: result["data"]["name"].as_str().unwrap(),
+                    ripeness: result["data"]["ripeness"].as_str().unwrap(),
+                },
+            );
 
             sleep(Duration::from_millis(500)).await;
-            self.do_share(result["data"]["banana_id"].as_i64().unwrap())
-                .await?;
+            if self.features.share {
+                self.do_share(result["data"]["banana_id"].as_i64().unwrap())
+                    .await?;
+            }
             sleep(Duration::from_millis(1000)).await;
             self.claim_ads_income(2).await?;
             cnt -= 1;
@@ -249,17 +383,20 @@ impl Banana {
     async fn do_share(&self, banana_id: i64) -> Result<(), Box<dyn std::error::Error>> {
         let (client, headers) = self.request();
 
-        let response = client
-            .post("https://interface.carv.io/banana/do_share")
-            .headers(headers)
-            .body(
-                json!({
-                    "banana_id": banana_id
-                })
-                .to_string(),
-            )
-            .send()
-            .await?;
+        let response = api::send(
+            &self.name,
+            "share",
+            client
+                .post(format!("{}do_share", api::API_BASE))
+                .headers(headers)
+                .body(
+                    json!({
+                        "banana_id": banana_id
+                    })
+                    .to_string(),
+                ),
+        )
+        .await?;
 
         utils::format_println(
             &self.name,
@@ -269,24 +406,36 @@ impl Banana {
         Ok(())
     }
 
-    async fn complete_quest(&self) -> Result<(), Box<dyn std::error::Error>> {
+    async fn get_quest_list(&self) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error>> {
         let (client, headers) = self.request();
-        let quest_list_resp = client
-            .get("https://interface.carv.io/banana/get_quest_list")
-            .headers(headers.clone())
-            .send()
-            .await?;
-
-        if quest_list_resp.status() != StatusCode::OK {
-            utils::format_error(&self.name, "get_quest_list failed");
-            // return Err(Box::new(BananaErr::QuestListErr));
-            return Ok(());
+        let response = api::send(
+            &self.name,
+            "quests",
+            client
+                .get(format!("{}get_quest_list", api::API_BASE))
+                .headers(headers),
+        )
+        .await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(Box::new(BananaErr::QuestListErr));
         }
 
-        let quest_list: serde_json::Value =
-            serde_json::from_str(&quest_list_resp.text().await?).unwrap();
-        let quest_list: Vec<serde_json::Value> =
-            serde_json::from_value(quest_list["data"]["quest_list"].clone()).unwrap();
+        let quest_list: serde_json::Value = response.json()?;
+        Ok(serde_json::from_value(
+            quest_list["data"]["quest_list"].clone(),
+        )?)
+    }
+
+    async fn complete_quest(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let (client, headers) = self.request();
+        let quest_list = match self.get_quest_list().await {
+            Ok(quest_list) => quest_list,
+            Err(_) => {
+                utils::format_error(&self.name, "get_quest_list failed");
+                return Ok(());
+            }
+        };
 
         for quest in quest_list.iter().filter(|x| {
             let is_achieved = x["is_achieved"].as_bool().unwrap();
@@ -305,12 +454,15 @@ impl Banana {
                     let body = json!({
                         "quest_id": quest_id
                     });
-                    let response = client
-                        .post("https://interface.carv.io/banana/achieve_quest")
-                        .headers(headers.clone())
-                        .body(body.to_string())
-                        .send()
-                        .await?;
+                    let response = api::send(
+                        &self.name,
+                        "quests",
+                        client
+                            .post(format!("{}achieve_quest", api::API_BASE))
+                            .headers(headers.clone())
+                            .body(body.to_string()),
+                    )
+                    .await?;
 
                     utils::format_println(
                         &self.name,
@@ -318,19 +470,34 @@ impl Banana {
                     );
 
                     sleep(Duration::from_secs(1)).await;
-                    let response = client
-                        .post("https://interface.carv.io/banana/claim_quest")
-                        .headers(headers.clone())
-                        .body(body.to_string())
-                        .send()
-                        .await?;
+                    let response = api::send(
+                        &self.name,
+                        "quests",
+                        client
+                            .post(format!("{}claim_quest", api::API_BASE))
+                            .headers(headers.clone())
+                            .body(body.to_string()),
+                    )
+                    .await?;
 
                     utils::format_println(
                         &self.name,
                         &format!("claim quest {}: {:?}", quest_id, response.status()),
                     );
+                    if response.status() == StatusCode::OK {
+                        events::emit(&self.name, Event::QuestClaimed);
+                    }
                 }
-                _ => {}
+                quest_type => notify::send_once(
+                    &self.name,
+                    notify::Kind::UnknownQuest,
+                    quest_type,
+                    &format!(
+                        "unknown quest type {}: {}",
+                        quest_type,
+                        quest["quest_name"].as_str().unwrap_or_default()
+                    ),
+                ),
             };
             sleep(Duration::from_secs(2)).await;
         }
@@ -343,19 +510,23 @@ impl Banana {
     async fn do_speedup(&self) -> Result<Option<i64>, Box<dyn std::error::Error>> {
         let (client, headers) = self.request();
 
-        let response = client
-            .post("https://interface.carv.io/banana/do_speedup")
-            .headers(headers)
-            .body("{}")
-            .send()
-            .await?;
+        let response = api::send(
+            &self.name,
+            "speedup",
+            client
+                .post(format!("{}do_speedup", api::API_BASE))
+                .headers(headers)
+                .body("{}"),
+        )
+        .await?;
 
         utils::format_println(&self.name, &format!("do_speedup: {:?}", response.status()));
 
         if response.status() == StatusCode::OK {
-            let data = &response.json::<serde_json::Value>().await?;
+            let data = &response.json::<serde_json::Value>()?;
             let code = data["code"].as_i64().unwrap();
             if code == 0i64 {
+                events::emit(&self.name, Event::SpeedUp);
                 let can_claim_time = data["data"]["lottery_info"]["last_countdown_start_time"]
                     .as_i64()
                     .unwrap()
@@ -375,7 +546,11 @@ impl Banana {
     }
 
     async fn loop_claim_quest_lottery(&self) -> Result<(), Box<dyn std::error::Error>> {
-        while let Some(true) = self.get_quest_is_claimed().await? {
+        loop {
+            let is_claimed = self.get_quest_is_claimed().await?;
+            if is_claimed != Some(true) {
+                break;
+            }
             self.claim_quest_lottery().await?;
             sleep(Duration::from_secs(1)).await;
         }
@@ -386,14 +561,17 @@ impl Banana {
     async fn get_quest_is_claimed(&self) -> Result<Option<bool>, Box<dyn std::error::Error>> {
         let (client, headers) = self.request();
 
-        let response = client
-            .get("https://interface.carv.io/banana/get_quest_list")
-            .headers(headers)
-            .send()
-            .await?;
+        let response = api::send(
+            &self.name,
+            "quests",
+            client
+                .get(format!("{}get_quest_list", api::API_BASE))
+                .headers(headers),
+        )
+        .await?;
 
         if response.status() == StatusCode::OK {
-            let data = response.json::<serde_json::Value>().await?;
+            let data = response.json::<serde_json::Value>()?;
             let code = data["code"].as_i64().expect("code is not a number");
             if code == 0i64 {
                 let is_claimed = data["data"]["is_claimed"].as_bool().unwrap();
@@ -408,43 +586,50 @@ impl Banana {
     async fn claim_quest_lottery(&self) -> Result<(), Box<dyn std::error::Error>> {
         let (client, headers) = self.request();
 
-        client
-            .post("https://interface.carv.io/banana/claim_quest_lottery")
-            .headers(headers)
-            .body("{}")
-            .send()
-            .await?;
+        api::send(
+            &self.name,
+            "quest_lottery",
+            client
+                .post(format!("{}claim_quest_lottery", api::API_BASE))
+                .headers(headers)
+                .body("{}"),
+        )
+        .await?;
 
         Ok(())
     }
 
     async fn claim_ads_income(&self, income_type: u8) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.features.ads {
+            return Ok(());
+        }
         let (client, headers) = self.request();
 
-        let response = client
-            .post("https://interface.carv.io/banana/claim_ads_income")
-            .headers(headers)
-            .body(
-                json!({
-                    "type": &income_type
-                })
-                .to_string(),
-            )
-            .send()
-            .await?;
+        let response = api::send(
+            &self.name,
+            "ads_income",
+            client
+                .post(format!("{}claim_ads_income", api::API_BASE))
+                .headers(headers)
+                .body(
+                    json!({
+                        "type": &income_type
+                    })
+                    .to_string(),
+                ),
+        )
+        .await?;
 
         let status = response.status();
         if status == StatusCode::OK {
-            let response = response.json::<serde_json::Value>().await?;
+            let response = response.json::<serde_json::Value>()?;
             if response["code"].as_i64().unwrap() == 0 {
+                let income = response["data"]["income"].as_f64().unwrap();
                 utils::format_println(
                     &self.name,
-                    &format!(
-                        "claim_ads_income_{}: {:?}",
-                        income_type,
-                        response["data"]["income"].as_f64().unwrap()
-                    ),
+                    &format!("claim_ads_income_{}: {:?}", income_type, income),
                 );
+                events::emit(&self.name, Event::AdsIncome(income));
             } else {
                 utils::format_error(
                     &self.name,
@@ -464,170 +649,922 @@ impl Banana {
 
         Ok(())
     }
+
+    async fn get_banana_list(&self) -> Result<Vec<BananaItem>, Box<dyn std::error::Error>> {
+        let (client, headers) = self.request();
+
+        let response = api::send(
+            &self.name,
+            "banana_list",
+            client
+                .get(format!("{}get_banana_list", api::API_BASE))
+                .headers(headers),
+        )
+        .await?;
+
+        if response.status() == StatusCode::OK {
+            let data = response.json::<serde_json::Value>()?;
+            if data["code"].as_i64() == Some(0) {
+                let list: Vec<BananaItem> =
+                    serde_json::from_value(data["data"]["banana_list"].clone())?;
+                return Ok(list);
+            }
+        }
+
+        utils::format_error(&self.name, "get_banana_list failed");
+        Ok(vec![])
+    }
+
+    async fn do_sell(&self, banana_id: i64, count: i32) -> Result<(), Box<dyn std::error::Error>> {
+        let (client, headers) = self.request();
+
+        let response = api::send(
+            &self.name,
+            "sell",
+            client
+                .post(format!("{}do_sell", api::API_BASE))
+                .headers(headers)
+                .body(
+                    json!({
+                        "bananaId": banana_id,
+                        "sellCount": count
+                    })
+                    .to_string(),
+                ),
+        )
+        .await?;
+
+        utils::format_println(
+            &self.name,
+            &format!("do_sell {} x{}: {:?}", banana_id, count, response.status()),
+        );
+
+        Ok(())
+    }
+
+    /// Sell every repeated banana, keeping one of each.
+    async fn sell_duplicates(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let bananas = self.get_banana_list().await?;
+        for banana in bananas {
+            if banana.count > 1 {
+                utils::format_println(
+                    &self.name,
+                    &format!("sell {} x{}", banana.name, banana.count - 1),
+                );
+                self.do_sell(banana.banana_id, banana.count - 1).await?;
+                sleep(Duration::from_secs(1)).await;
+            }
+        }
+        utils::format_println(&self.name, "sell_duplicates done!");
+
+        Ok(())
+    }
+
+    /// Ids of the bananas the account owns, the list also has the ones it has none of.
+    async fn owned_banana_ids(&self) -> Result<Vec<i64>, Box<dyn std::error::Error>> {
+        Ok(self
+            .get_banana_list()
+            .await?
+            .iter()
+            .filter(|b| b.count > 0)
+            .map(|b| b.banana_id)
+            .collect())
+    }
+
+    /// Log the goal progress and return the action to take if the goal is reached.
+    async fn check_goal(
+        &self,
+        goal: &Goal,
+    ) -> Result<Option<GoalAction>, Box<dyn std::error::Error>> {
+        let userinfo = self.get_user_info().await?;
+        let banana_ids = match goal.banana_id {
+            Some(_) => self.owned_banana_ids().await?,
+            None => vec![],
+        };
+        let progress = goal::Progress::new(goal, userinfo.peel, userinfo.usdt, &banana_ids);
+        utils::format_println(&self.name, &format!("goal progress: {}", progress));
+        state::set_goal(&self.name, progress.to_string());
+
+        Ok(progress.reached().then_some(goal.action))
+    }
 }
 
 async fn login(
+    name: &str,
     tg_url: &str,
     invite_code: &str,
+    proxy: Option<&str>,
 ) -> Result<(String, String), Box<dyn std::error::Error>> {
-    // let
-    let url = Url::parse(tg_url)?;
-    let f = url.fragment();
-    if let Some(f) = f {
-        let v = f.split('&').nth(0).unwrap();
-        let v = v.split('=').nth(1).unwrap();
-        let s = urlencoding::decode(v)?;
-        let body = json!({
-            "tgInfo": s.to_string(),
-            "InviteCode": invite_code,
-        });
+    let link = init_data::parse(tg_url)?;
+    if let Some(user) = &link.init_data.user {
+        log::debug!(
+            "{}: logging in as {}, web app {} on {}",
+            name,
+            user,
+            link.version.as_deref().unwrap_or("?"),
+            link.platform.as_deref().unwrap_or("?")
+        );
+    }
+    let body = json!({
+        "tgInfo": link.init_data.raw,
+        "InviteCode": invite_code,
+    });
+
+    let client = api::client(proxy)?;
+    let mut headers = HeaderMap::new();
+    utils::init_headers(&mut headers);
+
+    let mut attempts = 0;
+    let response = loop {
+        attempts += 1;
+        let response = api::send(
+            name,
+            "login",
+            client
+                .post(format!("{}login", api::API_BASE))
+                .headers(headers.clone())
+                .body(body.to_string()),
+        )
+        .await?;
+        if response.status() != StatusCode::TOO_MANY_REQUESTS
+            || ATTEMPTS.get().is_some_and(|max| attempts >= *max)
+        {
+            break response;
+        }
+        let retry_after = response.retry_after().unwrap_or(60);
+        utils::format_println(
+            name,
+            &format!("Rate limited. Retrying after {} seconds...", retry_after),
+        );
+        events::emit(name, Event::Retry { endpoint: "login" });
+        sleep(Duration::from_secs(retry_after)).await;
+    };
+    let status = response.status();
+    if status == StatusCode::OK {
+        let game_user_token = response
+            .headers()
+            .get_all("set-cookie")
+            .iter()
+            .filter_map(|v| {
+                let vv = v.to_str().expect("set cookie is not a string");
+                match regex::Regex::new(r"banana-game:user:token")
+                    .unwrap()
+                    .is_match(vv)
+                {
+                    true => Some(vv),
+                    false => None,
+                }
+            })
+            .next()
+            .unwrap();
+        let ck = cookie::Cookie::parse(game_user_token).unwrap();
+        let (name, value) = ck.name_value();
+        let name_value = name.to_owned() + "=" + value;
+
+        let val: serde_json::Value = response.json().unwrap();
+        let token = val["data"]["token"].as_str().unwrap();
+
+        return Ok((token.to_string(), name_value));
+    } else {
+        utils::format_error(name, &format!("login failed: {:?}", status));
+        // a rate limit says nothing about the link
+        if matches!(
+            status,
+            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+        ) {
+            return Err(Box::new(BananaErr::LinkRejected));
+        }
+    }
 
-        let client = reqwest::Client::new();
-        let mut headers = HeaderMap::new();
-        utils::init_headers(&mut headers);
+    Err(Box::new(BananaErr::LoginErr))
+}
 
-        let response = client
-            .post("https://interface.carv.io/banana/login")
-            .headers(headers.clone())
-            .body(body.to_string())
-            .send()
-            .await?;
-        let status = response.status();
-        if status == StatusCode::OK {
-            let game_user_token = response
-                .headers()
-                .get_all("set-cookie")
-                .iter()
-                .filter_map(|v| {
-                    let vv = v.to_str().expect("set cookie is not a string");
-                    match regex::Regex::new(r"banana-game:user:token")
-                        .unwrap()
-                        .is_match(vv)
-                    {
-                        true => Some(vv),
-                        false => None,
-                    }
-                })
-                .next()
-                .unwrap();
-            let ck = cookie::Cookie::parse(game_user_token).unwrap();
-            let (name, value) = ck.name_value();
-            let name_value = name.to_owned() + "=" + value;
+async fn goal_action(user: &Banana, goal: &Option<Goal>) -> Option<GoalAction> {
+    let goal = goal.as_ref()?;
+    user.check_goal(goal)
+        .await
+        .map_err(|err| {
+            utils::format_error(&user.name, &format!("check_goal err: {:?}", err));
+        })
+        .ok()
+        .flatten()
+}
 
-            let val: serde_json::Value = serde_json::from_str(&response.text().await?).unwrap();
-            let token = val["data"]["token"].as_str().unwrap();
+/// Run `command` requested through the control API or the terminal UI.
+async fn run_command(user: &Banana, command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Click => {
+            let userinfo = user.get_user_info().await?;
+            user.do_click(userinfo.max_click_count, userinfo.today_click_count)
+                .await
+        }
+        Command::Lottery => user.do_lottery().await,
+        Command::Quests => {
+            user.complete_quest().await?;
+            user.loop_claim_quest_lottery().await
+        }
+        Command::Claim => user.claim().await,
+    }
+}
 
-            return Ok((token.to_string(), name_value));
-        } else {
-            eprintln!("login failed: {:?}", status);
+/// Wait up to `duration` for the next claim, running the commands sent to the account
+/// meanwhile. A `Claim` command ends the wait early.
+async fn wait_for_claim(user: &Banana, control: &state::Control, duration: Duration) {
+    let deadline = tokio::time::Instant::now() + duration;
+    while let Some(command) = control.next_command(deadline).await {
+        if command == Command::Claim {
+            break;
+        }
+        control.wait_resumed().await;
+        if let Err(err) = run_command(user, command).await {
+            utils::format_error(&user.name, &format!("{:?} err: {:?}", command, err));
         }
     }
+    control.wait_resumed().await;
+}
 
-    Err(Box::new(BananaErr::LoginErr))
+/// Time (ms) until the next claim, without using the speedup.
+async fn countdown(user: &Banana) -> Option<i64> {
+    user.get_user_info()
+        .await
+        .ok()
+        .map(|userinfo| next_claim_in(&userinfo.lottery_info).max(0))
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    colog::init();
-    // read user token from file
-    let file_path = path::PathBuf::from(std::env::current_dir().unwrap()).join("user.json");
-    println!(
-        "Welcome to Banana Bot 🍌\nFree your hands now!\n\nOfficial website: {}",
-        "https://t.me/OfficialBananaBot/banana?startapp=referral=HHQJ6T4".yellow()
+async fn claim_loop(user: Arc<Banana>, userinfo: BananaUserInfo, goal: Option<Goal>) {
+    let name = user.name.clone();
+    let control = state::control(&name);
+    let rest_time = next_claim_in(&userinfo.lottery_info);
+
+    utils::format_println(
+        &name,
+        &format!("next claim is after: {}secs", rest_time.max(0) / 1000),
     );
-    info!("file_path: {:?}", file_path);
-    let users = utils::read_config_json(file_path.to_str().unwrap());
-    let mut copy_users: HashMap<String, User> = users.clone();
-
-    for (name, mut user) in users {
-        if user.access_token.is_none() || user.cookie_token.is_none() {
-            let default_invite_code = "".to_string();
-            let invite_code = user.invite_code.as_ref().unwrap_or(&default_invite_code);
-            let (access_token, cookie_token) = login(&user.link.as_ref().unwrap(), &invite_code)
+    events::emit(
+        &name,
+        Event::NextClaim(utils::get_current_timestamp() + rest_time.max(0)),
+    );
+
+    let rest_time = if rest_time > 0 {
+        rest_time as u64 + 1000
+    } else {
+        0
+    };
+    wait_for_claim(&user, &control, Duration::from_millis(rest_time)).await;
+    if user.features.claim {
+        let _ = user.claim().await.map_err(|err| {
+            utils::format_error(&name, &format!("err: {:?}", err));
+        });
+    }
+
+    loop {
+        control.wait_resumed().await;
+        let do_speedup_res = match goal_action(&user, &goal).await {
+            Some(GoalAction::Pause) => {
+                utils::format_println(&name, "goal reached, account paused");
+                state::set_status(&name, state::Status::Paused);
+                break;
+            }
+            Some(GoalAction::HarvestOnly) => countdown(&user).await,
+            Some(GoalAction::Sell) => {
+                user.sell_duplicates()
+                    .await
+                    .map_err(|err| {
+                        utils::format_error(&name, &format!("sell err: {:?}", err));
+                    })
+                    .ok();
+                match user.features.speedup {
+                    true => user.do_speedup().await.unwrap(),
+                    false => countdown(&user).await,
+                }
+            }
+            _ if !user.features.speedup => countdown(&user).await,
+            _ => user.do_speedup().await.unwrap(),
+        };
+
+        let rest_time = match do_speedup_res {
+            Some(rest_time) => rest_time / 1000 + 10,
+            None => 60 * 60 * 8 + 10,
+        } as u64;
+
+        utils::format_println(&name, &format!("next claim is after: {}secs", rest_time));
+        events::emit(
+            &name,
+            Event::NextClaim(utils::get_current_timestamp() + rest_time as i64 * 1000),
+        );
+        wait_for_claim(&user, &control, Duration::from_secs(rest_time)).await;
+
+        if user.features.claim {
+            user.claim()
                 .await
-                .unwrap();
-            user.access_token = Some(access_token);
-            user.cookie_token = Some(cookie_token);
-            // overwrite user config
-            copy_users.insert(name.clone().to_string(), user.clone());
-            utils::write_config_json(file_path.to_str().unwrap(), &copy_users);
+                .map_err(|err| {
+                    utils::format_error(&name, &format!("claim err: {:?}", err));
+                })
+                .ok();
+        }
+        if user.features.lottery {
+            user.do_lottery()
+                .await
+                .map_err(|err| {
+                    utils::format_error(&name, &format!("do_lottery err: {:?}", err));
+                })
+                .ok();
         }
+    }
+}
 
-        info!("name: {}, start", &name);
+async fn snapshot_loop(user: Arc<Banana>, history_dir: PathBuf) {
+    loop {
+        sleep(SNAPSHOT_INTERVAL).await;
+        match user.get_user_info().await {
+            Ok(userinfo) => record_snapshot(&history_dir, &user.name, &userinfo),
+            Err(err) => utils::format_error(
+                &user.name,
+                &format!("snapshot get_user_info err: {:?}", err),
+            ),
+        }
+    }
+}
 
-        let user = Banana::new(
-            name.clone(),
-            user.access_token.unwrap(),
-            user.cookie_token.unwrap(),
+/// Log in again shortly before the access token expires, as long as it is a JWT.
+async fn refresh_loop(client: Arc<Banana>, mut user: User, file_path: PathBuf) {
+    let name = client.name.clone();
+    loop {
+        let claims = client.token_claims();
+        state::set_token_expires(&name, claims.and_then(|c| c.exp).map(|exp| exp * 1000));
+        let wait = match refresh_in(claims) {
+            Some(wait) => wait,
+            None => return,
+        };
+        sleep(Duration::from_secs(wait.max(0) as u64)).await;
+
+        let result = login_account(&name, &mut user, &file_path)
+            .await
+            .map_err(|err| format!("{:?}", err));
+        match result {
+            Ok(()) => {
+                // the new cookies were kept by the jar while logging in
+                client.set_access_token(user.access_token.clone().unwrap_or_default());
+                let now = utils::get_current_timestamp() / 1000;
+                let valid_for = client
+                    .token_claims()
+                    .and_then(|c| c.expires_in(now))
+                    .map_or("?".to_string(), token::format_duration);
+                utils::format_println(
+                    &name,
+                    &format!("access token refreshed, valid for {}", valid_for),
+                );
+                state::set_status(&name, state::Status::Running);
+                if refresh_in(client.token_claims()).is_some_and(|secs| secs <= 0) {
+                    sleep(REFRESH_RETRY).await;
+                }
+            }
+            Err(err) => {
+                utils::format_error(&name, &format!("token refresh err: {}", err));
+                if state::status(&name) == Some(state::Status::NeedsNewLink) {
+                    return;
+                }
+                sleep(REFRESH_RETRY).await;
+            }
+        }
+    }
+}
+
+/// The account's own proxy, or the global one.
+fn account_proxy(user: &User) -> Option<&str> {
+    user.proxy.as_deref().or(settings::get().proxy.as_deref())
+}
+
+/// Age of the initData of a link in seconds, `None` when it can't be told.
+fn link_age(link: &str) -> Option<i64> {
+    let auth_date = init_data::parse(link).ok()?.init_data.auth_date?;
+    Some(utils::get_current_timestamp() / 1000 - auth_date)
+}
+
+/// Seconds after which a link is likely to be refused, `BANANA_LINK_MAX_AGE_HOURS`.
+fn max_link_age() -> i64 {
+    settings::get().link_max_age_hours * 3600
+}
+
+/// Warn when the link of an account is about to get stale or already is, it is only needed
+/// to log in again.
+fn check_link_age(name: &str, user: &User) {
+    // without tokens it logs in right away, which checks the link itself
+    if user.access_token.is_none() {
+        return;
+    }
+    let age = match user.link.as_deref().and_then(link_age) {
+        Some(age) => age,
+        None => return,
+    };
+    let max_age = max_link_age();
+    let message = if age >= max_age {
+        format!(
+            "the link is {}h old, the next login may need a new one",
+            age / 3600
+        )
+    } else if age >= max_age - settings::get().link_warn_hours * 3600 {
+        format!(
+            "the link gets stale in {}min, paste a new one before the tokens expire",
+            (max_age - age) / 60
+        )
+    } else {
+        return;
+    };
+    utils::format_warn(name, &message);
+    notify::send_once(name, notify::Kind::NeedsNewLink, "link_age", &message);
+}
+
+/// Stop trying to log in with a link the server won't accept.
+fn needs_new_link(name: &str, message: &str) {
+    utils::format_error(name, message);
+    state::set_status(name, state::Status::NeedsNewLink);
+    notify::send_once(name, notify::Kind::NeedsNewLink, "needs_new_link", message);
+}
+
+/// Log in with the account's link and save the new tokens to the config.
+async fn login_account(
+    name: &str,
+    user: &mut User,
+    file_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let link = user.link.clone().ok_or("no link to log in with")?;
+    // the server decides, an old link is only worth a warning
+    if let Some(age) = link_age(&link).filter(|age| *age >= max_link_age()) {
+        utils::format_warn(
+            name,
+            &format!(
+                "the link is {}h old, logging in with it may be refused",
+                age / 3600
+            ),
         );
+    }
+    // without one, use the referral code the link was opened with
+    let invite_code = user
+        .invite_code
+        .clone()
+        .filter(|code| !code.is_empty())
+        .or_else(|| {
+            let start_param = init_data::parse(&link).ok()?.init_data.start_param?;
+            Some(init_data::referral_code(&start_param))
+        })
+        .unwrap_or_default();
+    state::set_status(name, state::Status::LoggingIn);
+    let proxy = account_proxy(user);
+    let (access_token, cookie_token) = match login(name, &link, &invite_code, proxy).await {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            notify::send(
+                name,
+                notify::Kind::AuthFailure,
+                &format!("login err: {:?}", err),
+            );
+            // the server refused the link itself, logging in again won't help
+            if matches!(err.downcast_ref(), Some(BananaErr::LinkRejected)) {
+                needs_new_link(name, "the server refused the link, paste a new one");
+            }
+            return Err(err);
+        }
+    };
+    // only the tokens, the account may have been edited since it was loaded
+    config::save_tokens(file_path, name, &access_token, &cookie_token)?;
+    user.access_token = Some(access_token);
+    user.cookie_token = Some(cookie_token);
+    Ok(())
+}
 
-        let userinfo = user
-            .get_user_info()
-            .await
-            .expect(&format!("{} get_user_info failed", &name));
+/// Seconds before the access token should be refreshed, `None` when it isn't a JWT.
+fn refresh_in(claims: Option<token::Claims>) -> Option<i64> {
+    let expires_in = claims?.expires_in(utils::get_current_timestamp() / 1000)?;
+    Some(expires_in - settings::get().token_refresh_mins * 60)
+}
+
+/// The API client of an account, logging in first if it has no tokens yet or they are
+/// about to expire.
+async fn connect(
+    name: &str,
+    mut user: User,
+    file_path: &Path,
+) -> Result<Banana, Box<dyn std::error::Error>> {
+    let expiring = refresh_in(user.access_token.as_deref().and_then(token::claims))
+        .is_some_and(|secs| secs <= 0);
+    if expiring {
+        utils::format_println(name, "access token about to expire, logging in again");
+    }
+    if user.access_token.is_none() || user.cookie_token.is_none() || expiring {
+        login_account(name, &mut user, file_path).await?;
+    }
+    let mut banana = Banana::new(
+        name.to_string(),
+        user.access_token.clone().unwrap(),
+        user.cookie_token.clone().unwrap(),
+        account_proxy(&user),
+    )?;
+    banana.features = user.features.clone();
+    Ok(banana)
+}
+
+/// Log in if needed, then click, complete quests and claim the quest lottery as far as
+/// the account's goal allows. `Ok(None)` when the goal paused the account.
+async fn daily_routine(
+    name: &str,
+    user: User,
+    goal: &Option<Goal>,
+    file_path: &Path,
+    history_dir: &Path,
+) -> Result<Option<(Banana, BananaUserInfo)>, Box<dyn std::error::Error>> {
+    let user = connect(name, user, file_path)
+        .await
+        .map_err(|err| format!("login err: {:?}", err))?;
+
+    info!("name: {}, start", name);
+
+    let userinfo = user
+        .get_user_info()
+        .await
+        .map_err(|err| format!("get_user_info err: {:?}", err))?;
+    state::set_status(name, state::Status::Running);
+
+    let action = goal_action(&user, goal).await;
+    match action {
+        Some(GoalAction::Pause) => {
+            utils::format_println(name, "goal reached, account paused");
+            state::set_status(name, state::Status::Paused);
+            return Ok(None);
+        }
+        Some(GoalAction::Sell) => user.sell_duplicates().await?,
+        _ => {}
+    }
 
+    if user.features.click && (action.is_none() || action == Some(GoalAction::Sell)) {
         user.do_click(userinfo.max_click_count, userinfo.today_click_count)
+            .await?;
+    }
+
+    if action != Some(GoalAction::HarvestOnly) {
+        if user.features.quests {
+            user.complete_quest().await?;
+        }
+        if user.features.quest_lottery {
+            user.loop_claim_quest_lottery().await?;
+        }
+    }
+
+    record_snapshot(history_dir, name, &userinfo);
+    Ok(Some((user, userinfo)))
+}
+
+/// Spawn the daily routine of one account followed by its claim loop, so that `main()` keeps
+/// handling actions while it runs. The task ends when the account failed to start or is
+/// paused by its goal.
+fn start_account(name: &str, user: User, file_path: &Path, history_dir: &Path) -> JoinHandle<()> {
+    state::register(name);
+    check_link_age(name, &user);
+    let name = name.to_string();
+    let history_dir = history_dir.to_path_buf();
+    let file_path = file_path.to_path_buf();
+    tokio::spawn(async move {
+        let goal = user.goal.clone();
+        let account = user.clone();
+        let started = daily_routine(&name, user, &goal, &file_path, &history_dir)
             .await
-            .unwrap();
+            .map_err(|err| err.to_string());
+        let (user, userinfo) = match started {
+            Ok(Some(started)) => started,
+            Ok(None) => return,
+            Err(err) => {
+                utils::format_error(&name, &err);
+                state::set_failed(&name);
+                return;
+            }
+        };
+
+        let user = Arc::new(user);
+        let refresh = async {
+            refresh_loop(user.clone(), account, file_path).await;
+            // without a JWT or a valid link there is nothing to refresh, keep claiming
+            std::future::pending::<()>().await
+        };
+        // the claim loop only returns once the goal paused the account, which ends it
+        tokio::select! {
+            _ = snapshot_loop(user.clone(), history_dir) => {}
+            _ = refresh => {}
+            _ = claim_loop(user.clone(), userinfo, goal) => {}
+        }
+    })
+}
 
-        user.complete_quest().await.unwrap();
-        user.loop_claim_quest_lottery().await.unwrap();
+/// A started account and its task.
+struct Running {
+    settings: serde_json::Value,
+    task: JoinHandle<()>,
+}
 
-        let arc_user = Arc::new(user);
+/// What an account was started with, apart from the tokens it saves itself.
+fn account_settings(user: &User) -> serde_json::Value {
+    let mut user = user.clone();
+    user.access_token = None;
+    user.cookie_token = None;
+    serde_json::to_value(user).unwrap_or_default()
+}
 
-        tokio::spawn(async move {
-            let can_claim_time = userinfo.lottery_info.last_countdown_start_time
-                + (userinfo.lottery_info.countdown_interval as i64 * 60000);
-            let rest_time = can_claim_time - utils::get_current_timestamp();
+fn stop_account(tasks: &mut HashMap<String, Running>, name: &str) {
+    if let Some(running) = tasks.remove(name) {
+        running.task.abort();
+    }
+    state::remove(name);
+}
 
-            utils::format_println(
-                &name,
-                &format!("next claim is after: {}secs", (rest_time.max(0) / 1000) | 0),
-            );
+/// Re-read the config: start accounts that were added, stop the ones that were removed and
+/// restart the ones whose settings changed.
+async fn reload(
+    tasks: &mut HashMap<String, Running>,
+    cli: &cli::Cli,
+    file_path: &Path,
+    history_dir: &Path,
+) {
+    let users = match config::load(file_path) {
+        Ok(config) => cli.filter(config.accounts),
+        Err(err) => {
+            log::error!("reload err: {}", err);
+            return;
+        }
+    };
+    log::debug!("reloading {:?}", file_path);
+
+    let removed: Vec<String> = tasks
+        .keys()
+        .filter(|name| !users.contains_key(*name))
+        .cloned()
+        .collect();
+    for name in removed {
+        stop_account(tasks, &name);
+        cookies::remove(&name);
+        info!("name: {}, removed", name);
+    }
 
-            if rest_time > 0 {
-                sleep(Duration::from_millis(rest_time as u64 + 1000u64)).await;
+    for (name, user) in users {
+        let settings = account_settings(&user);
+        match tasks.get(&name) {
+            Some(running) if running.settings == settings => continue,
+            Some(_) => {
+                stop_account(tasks, &name);
+                info!("name: {}, settings changed, restarting", name);
             }
-            let _ = arc_user.claim().await.map_err(|err| {
-                utils::format_error(&name, &format!("err: {:?}", err));
-            });
+            None => {}
+        }
+        let task = start_account(&name, user, file_path, history_dir);
+        tasks.insert(name, Running { settings, task });
+    }
+}
 
-            loop {
-                let do_speedup_res = arc_user.do_speedup().await.unwrap();
+/// The accounts selected on the command line, exits when the config can't be loaded.
+fn load_users(cli: &cli::Cli, file_path: &Path) -> HashMap<String, User> {
+    match config::load(file_path) {
+        Ok(config) => cli.filter(config.accounts),
+        Err(err) => {
+            config::explain(&err);
+            std::process::exit(1);
+        }
+    }
+}
 
-                let rest_time = match do_speedup_res {
-                    Some(rest_time) => rest_time / 1000 + 10,
-                    None => 60 * 60 * 8 + 10,
-                } as u64;
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = cli::Cli::parse();
+    let file_path = std::env::current_dir().unwrap().join(&cli.config);
+    let history_dir: PathBuf = std::env::current_dir().unwrap().join("history");
+
+    // a broken config is reported by the commands that need the accounts
+    let global = config::load(&file_path)
+        .map(|config| config.global)
+        .unwrap_or_default();
+    let mut settings = settings::Settings::load(&global);
+    if let Some(log_format) = cli.log_format {
+        settings.log_format = log_format;
+    }
+    settings::init(settings);
+    if let Some(path) = &settings::get().secrets_file {
+        if let Err(err) = secrets::init(path) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
 
-                utils::format_println(
-                    &name,
-                    &format!("next claim is after: {}secs", rest_time.max(0)),
-                );
-                sleep(Duration::from_secs(rest_time)).await;
+    match cli.command.as_ref().unwrap_or(&cli::Command::Run) {
+        cli::Command::Run => run(&cli, &file_path, &history_dir).await,
+        cli::Command::Once => {
+            if !once(&cli, &file_path, &history_dir).await {
+                std::process::exit(1);
+            }
+        }
+        cli::Command::Stats => history::print_report(&history_dir, &cli.accounts),
+        cli::Command::Accounts(command) => cli::accounts(command, &file_path),
+        cli::Command::Encrypt => cli::encrypt(&file_path),
+        cli::Command::Validate => {
+            if !validate::run(&file_path) {
+                std::process::exit(1);
+            }
+        }
+        command => {
+            logging::init(settings::get().log_format);
+            let users = load_users(&cli, &file_path);
+            let unknown = cli.unknown(&users);
+            for name in &unknown {
+                eprintln!("unknown account: {}", name);
+            }
+            let ok = match command {
+                cli::Command::Login { alias } => cli::login(alias, users, &file_path).await,
+                cli::Command::Status => cli::status(users, &file_path).await,
+                cli::Command::Inventory => cli::inventory(users, &file_path).await,
+                cli::Command::Quests => cli::quests(users, &file_path).await,
+                _ => unreachable!(),
+            };
+            if !ok || !unknown.is_empty() {
+                std::process::exit(1);
+            }
+        }
+    }
 
-                arc_user
-                    .claim()
-                    .await
-                    .map_err(|err| {
-                        utils::format_error(&name, &format!("claim err: {:?}", err));
-                    })
-                    .ok();
-                arc_user
-                    .do_lottery()
-                    .await
-                    .map_err(|err| {
-                        utils::format_error(&name, &format!("do_lottery err: {:?}", err));
-                    })
-                    .ok();
+    Ok(())
+}
+
+/// One pass over an account for `once`: the daily routine, then claim if the countdown has
+/// elapsed, speed up and harvest. `Ok(None)` when the goal paused the account.
+async fn once_account(
+    name: &str,
+    user: User,
+    file_path: &Path,
+    history_dir: &Path,
+) -> Result<Option<BananaUserInfo>, Box<dyn std::error::Error>> {
+    let goal = user.goal.clone();
+    let (user, userinfo) = match daily_routine(name, user, &goal, file_path, history_dir).await? {
+        Some(started) => started,
+        None => return Ok(None),
+    };
+
+    let features = user.features.clone();
+    if features.claim && next_claim_in(&userinfo.lottery_info) <= 0 {
+        user.claim().await?;
+    }
+    if features.speedup && goal_action(&user, &goal).await != Some(GoalAction::HarvestOnly) {
+        let rest_time = user.do_speedup().await?;
+        if features.claim && rest_time.is_some_and(|rest_time| rest_time <= 0) {
+            user.claim().await?;
+        }
+    }
+    if features.lottery {
+        user.do_lottery().await?;
+    }
+
+    Ok(Some(user.get_user_info().await?))
+}
+
+/// Run every account once without waiting for claims, then print a summary.
+/// Returns false when an account failed.
+async fn once(cli: &cli::Cli, file_path: &Path, history_dir: &Path) -> bool {
+    logging::init(settings::get().log_format);
+    let _ = ATTEMPTS.set(ONCE_ATTEMPTS);
+    let mut users: Vec<_> = load_users(cli, file_path).into_iter().collect();
+    users.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut results = vec![];
+    for (name, user) in users {
+        state::register(&name);
+        let result = once_account(&name, user, file_path, history_dir).await;
+        if let Err(err) = &result {
+            utils::format_error(&name, &err.to_string());
+        }
+        results.push((name, result));
+    }
+
+    println!(
+        "\n{:<20} {:<8} {:>12} {:>10}",
+        "account", "result", "peel", "usdt"
+    );
+    let mut ok = true;
+    for (name, result) in results {
+        match result {
+            Ok(Some(info)) => println!(
+                "{:<20} {:<8} {:>12.2} {:>10.4}",
+                name, "ok", info.peel, info.usdt
+            ),
+            Ok(None) => println!("{:<20} {:<8}", name, "paused"),
+            Err(err) => {
+                ok = false;
+                println!("{:<20} {:<8} {}", name, "failed", err);
             }
+        }
+    }
+    ok
+}
+
+async fn run(cli: &cli::Cli, file_path: &Path, history_dir: &Path) {
+    logging::init(settings::get().log_format);
+    if let Some(addr) = settings::get().metrics_addr {
+        http::spawn(
+            "metrics",
+            addr,
+            Arc::new(|req| Box::pin(metrics::handle(req))),
+        );
+    }
+    if let Some(addr) = settings::get().dashboard_addr {
+        http::spawn(
+            "dashboard",
+            addr,
+            Arc::new(|req| Box::pin(dashboard::handle(req))),
+        );
+    }
+    if let Some(log_dir) = &settings::get().log_dir {
+        let settings = settings::get();
+        if let Err(err) = log_file::init(log_dir, settings.log_max_size, settings.log_keep) {
+            log::error!("log dir {:?} err: {:?}", log_dir, err);
+        }
+    }
+    tokio::spawn(async {
+        let settings = settings::get();
+        summary::run_scheduler(settings.summary_time, &settings.reports_dir).await;
+    });
+
+    if let Some(url) = &settings::get().webhook_url {
+        let settings = settings::get();
+        webhook::spawn(webhook::Webhook {
+            url: url.clone(),
+            events: settings.webhook_events.clone(),
+            batch: Duration::from_secs(settings.webhook_batch_secs),
+            min_interval: Duration::from_secs(settings.webhook_min_interval_secs),
+        });
+    }
+    if let (Some(token), Some(chat_id)) = (
+        &settings::get().telegram_token,
+        &settings::get().telegram_chat_id,
+    ) {
+        let settings = settings::get();
+        telegram::spawn(telegram::Telegram {
+            api_base: settings.telegram_api_base.clone(),
+            token: token.clone(),
+            chat_id: chat_id.clone(),
+            events: settings.telegram_events.clone(),
+            templates: settings.telegram_templates.clone(),
         });
+    }
+    if settings::get().webhook_url.is_some() || settings::get().telegram_token.is_some() {
+        tokio::spawn(notify::run_failing_check(settings::get().failing_minutes));
+    }
 
+    if settings::get().tui {
+        std::thread::spawn(|| tui::run());
+    }
+
+    if settings::get().log_format == logging::LogFormat::Human && !settings::get().tui {
+        println!(
+            "Welcome to Banana Bot 🍌\nFree your hands now!\n\nOfficial website: {}",
+            "https://t.me/OfficialBananaBot/banana?startapp=referral=HHQJ6T4".yellow()
+        );
+    }
+    info!("file_path: {:?}", file_path);
+    let users = load_users(cli, file_path);
+    for name in users.keys() {
+        state::register(name);
+    }
+
+    let mut actions = control::init();
+    if let Some(addr) = settings::get().control_addr {
+        http::spawn(
+            "control",
+            addr,
+            Arc::new(|req| Box::pin(control::handle(req))),
+        );
+    }
+    if settings::get().config_watch_secs > 0 {
+        tokio::spawn(config::watch(
+            file_path.to_path_buf(),
+            Duration::from_secs(settings::get().config_watch_secs),
+        ));
+    }
+
+    // accounts started so far
+    let mut tasks: HashMap<String, Running> = HashMap::new();
+    for (name, user) in users {
+        let settings = account_settings(&user);
+        let task = start_account(&name, user, file_path, history_dir);
+        tasks.insert(name, Running { settings, task });
         sleep(Duration::from_secs(1)).await;
     }
 
     // at most 7 days
-    sleep(Duration::from_secs(60 * 60 * 24 * 7)).await;
-
-    Ok(())
+    let deadline = sleep(Duration::from_secs(60 * 60 * 24 * 7));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            Some(action) = actions.recv() => match action {
+                control::Action::Reload => reload(&mut tasks, cli, file_path, history_dir).await,
+                control::Action::Shutdown => {
+                    info!("shutting down");
+                    break;
+                }
+            },
+        }
+    }
 }
@@ -0,0 +1,204 @@
+use crate::events::Event;
+use crate::http::{Request, Response};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{LazyLock, Mutex};
+
+#[derive(Default)]
+struct Metrics {
+    /// (account, endpoint, status)
+    requests: BTreeMap<(String, String, String), u64>,
+    /// (account, endpoint, code)
+    api_errors: BTreeMap<(String, String, i64), u64>,
+    /// (account, endpoint)
+    retries: BTreeMap<(String, String), u64>,
+    clicks: BTreeMap<String, u64>,
+    claims: BTreeMap<String, u64>,
+    speedups: BTreeMap<String, u64>,
+    /// (account, ripeness)
+    harvests: BTreeMap<(String, String), u64>,
+    quests: BTreeMap<String, u64>,
+    ads_income: BTreeMap<String, f64>,
+    errors: BTreeMap<String, u64>,
+    peel: BTreeMap<String, f64>,
+    usdt: BTreeMap<String, f64>,
+    next_claim: BTreeMap<String, i64>,
+}
+
+static METRICS: LazyLock<Mutex<Metrics>> = LazyLock::new(|| Mutex::new(Metrics::default()));
+
+pub fn on_request(account: &str, endpoint: &str, status: Option<u16>, code: Option<i64>) {
+    let mut m = METRICS.lock().unwrap();
+    let status = status.map_or("error".to_string(), |s| s.to_string());
+    *m.requests
+        .entry((account.to_string(), endpoint.to_string(), status))
+        .or_default() += 1;
+    if let Some(code) = code.filter(|c| *c != 0) {
+        *m.api_errors
+            .entry((account.to_string(), endpoint.to_string(), code))
+            .or_default() += 1;
+    }
+}
+
+pub fn on_event(account: &str, event: &Event) {
+    let mut m = METRICS.lock().unwrap();
+    let account = account.to_string();
+    match event {
+        Event::Clicked(count) => *m.clicks.entry(account).or_default() += *count as u64,
+        Event::Claimed => *m.claims.entry(account).or_default() += 1,
+        Event::SpeedUp => *m.speedups.entry(account).or_default() += 1,
+        Event::Harvested { ripeness, .. } => {
+            *m.harvests
+                .entry((account, ripeness.to_string()))
+                .or_default() += 1
+        }
+        Event::QuestClaimed => *m.quests.entry(account).or_default() += 1,
+        Event::AdsIncome(income) => *m.ads_income.entry(account).or_default() += income,
+        Event::UserInfo(info) => {
+            m.peel.insert(account.clone(), info.peel);
+            m.usdt.insert(account, info.usdt);
+        }
+        Event::NextClaim(timestamp) => {
+            m.next_claim.insert(account, *timestamp);
+        }
+        Event::Retry { endpoint } => {
+            *m.retries
+                .entry((account, endpoint.to_string()))
+                .or_default() += 1
+        }
+        Event::Error(_) => *m.errors.entry(account).or_default() += 1,
+        Event::ClickLimit(_) => {}
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn family<V: std::fmt::Display>(
+    out: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+    samples: impl Iterator<Item = (String, V)>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+    }
+}
+
+fn account_label(account: &str) -> String {
+    format!("account=\"{}\"", escape(account))
+}
+
+/// Render every metric in the Prometheus text exposition format.
+pub fn render() -> String {
+    let m = METRICS.lock().unwrap();
+    let now = crate::utils::get_current_timestamp();
+    let mut out = String::new();
+
+    family(
+        &mut out,
+        "banana_requests_total",
+        "counter",
+        "API requests by endpoint and HTTP status.",
+        m.requests.iter().map(|((a, e, s), v)| {
+            (
+                format!("{},endpoint=\"{}\",status=\"{}\"", account_label(a), e, s),
+                v,
+            )
+        }),
+    );
+    family(
+        &mut out,
+        "banana_api_errors_total",
+        "counter",
+        "API responses with a non-zero code.",
+        m.api_errors.iter().map(|((a, e, c), v)| {
+            (
+                format!("{},endpoint=\"{}\",code=\"{}\"", account_label(a), e, c),
+                v,
+            )
+        }),
+    );
+    family(
+        &mut out,
+        "banana_retries_total",
+        "counter",
+        "Requests retried after a failure or rate limit.",
+        m.retries
+            .iter()
+            .map(|((a, e), v)| (format!("{},endpoint=\"{}\"", account_label(a), e), v)),
+    );
+    for (name, help, values) in [
+        ("banana_clicks_total", "Clicks done.", &m.clicks),
+        ("banana_claims_total", "Lottery claims.", &m.claims),
+        ("banana_speedups_total", "Speedups used.", &m.speedups),
+        ("banana_quests_claimed_total", "Quests claimed.", &m.quests),
+        ("banana_errors_total", "Errors logged.", &m.errors),
+    ] {
+        family(
+            &mut out,
+            name,
+            "counter",
+            help,
+            values.iter().map(|(a, v)| (account_label(a), v)),
+        );
+    }
+    family(
+        &mut out,
+        "banana_harvests_total",
+        "counter",
+        "Bananas harvested by ripeness.",
+        m.harvests.iter().map(|((a, r), v)| {
+            (
+                format!("{},ripeness=\"{}\"", account_label(a), escape(r)),
+                v,
+            )
+        }),
+    );
+    family(
+        &mut out,
+        "banana_ads_income_total",
+        "counter",
+        "Ads income claimed.",
+        m.ads_income.iter().map(|(a, v)| (account_label(a), v)),
+    );
+    family(
+        &mut out,
+        "banana_peel",
+        "gauge",
+        "Peel balance.",
+        m.peel.iter().map(|(a, v)| (account_label(a), v)),
+    );
+    family(
+        &mut out,
+        "banana_usdt",
+        "gauge",
+        "USDT balance.",
+        m.usdt.iter().map(|(a, v)| (account_label(a), v)),
+    );
+    family(
+        &mut out,
+        "banana_next_claim_seconds",
+        "gauge",
+        "Seconds until the next claim.",
+        m.next_claim
+            .iter()
+            .map(|(a, t)| (account_label(a), (t - now).max(0) / 1000)),
+    );
+
+    out
+}
+
+pub async fn handle(request: Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/metrics") => Response::new(200, "text/plain; version=0.0.4", render()),
+        _ => Response::not_found(),
+    }
+}
@@ -0,0 +1,184 @@
+use crate::events::Event;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// login failed or the tokens were rejected
+    AuthFailure,
+    /// the link is about to expire or has expired
+    NeedsNewLink,
+    RareHarvest,
+    /// a quest the bot does not know how to complete
+    UnknownQuest,
+    /// every request failed for `BANANA_FAILING_MINUTES`
+    AccountFailing,
+    DailySummary,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 6] = [
+        Kind::AuthFailure,
+        Kind::NeedsNewLink,
+        Kind::RareHarvest,
+        Kind::UnknownQuest,
+        Kind::AccountFailing,
+        Kind::DailySummary,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::AuthFailure => "auth_failure",
+            Kind::NeedsNewLink => "needs_new_link",
+            Kind::RareHarvest => "rare_harvest",
+            Kind::UnknownQuest => "unknown_quest",
+            Kind::AccountFailing => "account_failing",
+            Kind::DailySummary => "daily_summary",
+        }
+    }
+}
+
+impl FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Kind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| format!("unknown notification: {}", s))
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Notification {
+    pub kind: Kind,
+    /// `None` for notifications about every account, like the daily summary
+    pub account: Option<String>,
+    pub message: String,
+    pub timestamp: i64,
+}
+
+/// One channel per sink; every notification is delivered to all of them.
+static SINKS: LazyLock<Mutex<Vec<UnboundedSender<Notification>>>> =
+    LazyLock::new(|| Mutex::new(Vec::new()));
+/// (account, key) pairs already reported by [`send_once`]
+static SENT: LazyLock<Mutex<HashSet<(String, String)>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+/// The failed requests of an account since its last successful one.
+struct Streak {
+    first: i64,
+    last: i64,
+    notified: bool,
+}
+
+/// account -> its failure streak
+static FAILING: LazyLock<Mutex<HashMap<String, Streak>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Register a new sink and return the receiving end of its channel.
+pub fn subscribe() -> UnboundedReceiver<Notification> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    SINKS.lock().unwrap().push(tx);
+    rx
+}
+
+fn dispatch(account: Option<&str>, kind: Kind, message: &str) {
+    let notification = Notification {
+        kind,
+        account: account.map(str::to_string),
+        message: message.to_string(),
+        timestamp: crate::utils::get_current_timestamp(),
+    };
+    SINKS
+        .lock()
+        .unwrap()
+        .retain(|tx| tx.send(notification.clone()).is_ok());
+}
+
+pub fn send(account: &str, kind: Kind, message: &str) {
+    dispatch(Some(account), kind, message);
+}
+
+/// Send a notification that is not about one account.
+pub fn broadcast(kind: Kind, message: &str) {
+    dispatch(None, kind, message);
+}
+
+/// Like [`send`], but only the first time `key` is reported for the account.
+pub fn send_once(account: &str, kind: Kind, key: &str, message: &str) {
+    if SENT
+        .lock()
+        .unwrap()
+        .insert((account.to_string(), key.to_string()))
+    {
+        send(account, kind, message);
+    }
+}
+
+fn is_rare(ripeness: &str) -> bool {
+    crate::settings::get()
+        .rare_ripeness
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(ripeness))
+}
+
+pub fn on_event(account: &str, event: &Event) {
+    if let Event::Harvested { name, ripeness } = event {
+        if is_rare(ripeness) {
+            send(
+                account,
+                Kind::RareHarvest,
+                &format!("harvested {} ({})", name, ripeness),
+            );
+        }
+    }
+}
+
+/// Record the outcome of an API request of `account`: a successful one ends its failure
+/// streak.
+pub fn on_request(account: &str, ok: bool) {
+    let mut failing = FAILING.lock().unwrap();
+    if ok {
+        failing.remove(account);
+    } else {
+        let now = crate::utils::get_current_timestamp();
+        failing
+            .entry(account.to_string())
+            .or_insert(Streak {
+                first: now,
+                last: now,
+                notified: false,
+            })
+            .last = now;
+    }
+}
+
+/// Report accounts whose requests all failed for `minutes`, once per failure streak. An
+/// account that stopped making requests is not reported.
+pub async fn run_failing_check(minutes: u64) {
+    let limit = minutes as i64 * 60 * 1000;
+    loop {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        let now = crate::utils::get_current_timestamp();
+        let mut failing = Vec::new();
+        for (account, streak) in FAILING.lock().unwrap().iter_mut() {
+            if !streak.notified && now - streak.first >= limit && now - streak.last < limit {
+                streak.notified = true;
+                failing.push(account.clone());
+            }
+        }
+        for account in failing {
+            send(
+                &account,
+                Kind::AccountFailing,
+                &format!("no successful request for {} minutes", minutes),
+            );
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::utils::User;
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// The secret part of an account, kept out of the config when the store is enabled.
+#[derive(Deserialize, Serialize, Clone, Default, Debug)]
+struct Secret {
+    link: Option<String>,
+    access_token: Option<String>,
+    cookie_token: Option<String>,
+    /// the cookie jar of the account, as `cookies` saves it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cookies: Option<String>,
+}
+
+/// On-disk shape of the store; `data` is the AES-256-GCM encrypted JSON of all secrets.
+#[derive(Deserialize, Serialize)]
+struct StoreFile {
+    kdf: String,
+    salt: String,
+    nonce: String,
+    data: String,
+}
+
+struct Store {
+    path: PathBuf,
+    salt: [u8; 16],
+    key: Key<Aes256Gcm>,
+    secrets: Mutex<BTreeMap<String, Secret>>,
+}
+
+static STORE: OnceLock<Store> = OnceLock::new();
+
+fn passphrase() -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var("BANANA_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err("set BANANA_PASSPHRASE to open the secrets store".to_string());
+    }
+    rpassword::prompt_password("secrets passphrase: ").map_err(|err| err.to_string())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>, String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| err.to_string())?;
+    Ok(key.into())
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, String> {
+    STANDARD.decode(value).map_err(|err| err.to_string())
+}
+
+fn open(path: &Path) -> Result<Store, String> {
+    let passphrase = passphrase()?;
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            let mut salt = [0u8; 16];
+            rand::thread_rng().fill(&mut salt);
+            return Ok(Store {
+                path: path.to_path_buf(),
+                salt,
+                key: derive_key(&passphrase, &salt)?,
+                secrets: Mutex::new(BTreeMap::new()),
+            });
+        }
+        Err(err) => return Err(err.to_string()),
+    };
+
+    let file: StoreFile = serde_json::from_str(&content).map_err(|err| err.to_string())?;
+    let salt: [u8; 16] = decode(&file.salt)?
+        .try_into()
+        .map_err(|_| "invalid salt".to_string())?;
+    let key = derive_key(&passphrase, &salt)?;
+    let nonce = decode(&file.nonce)?;
+    if nonce.len() != 12 {
+        return Err("invalid nonce".to_string());
+    }
+    let plaintext = Aes256Gcm::new(&key)
+        .decrypt(Nonce::from_slice(&nonce), decode(&file.data)?.as_ref())
+        .map_err(|_| "wrong passphrase or corrupted store".to_string())?;
+    let secrets = serde_json::from_slice(&plaintext).map_err(|err| err.to_string())?;
+
+    Ok(Store {
+        path: path.to_path_buf(),
+        salt,
+        key,
+        secrets: Mutex::new(secrets),
+    })
+}
+
+/// Open (or start) the encrypted store at `path`; links and tokens are read from and written
+/// to it instead of the config from now on.
+pub fn init(path: &Path) -> Result<(), String> {
+    let store = open(path).map_err(|err| format!("secrets {:?}: {}", path, err))?;
+    let _ = STORE.set(store);
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    STORE.get().is_some()
+}
+
+/// Complete the accounts loaded from the config with their secrets.
+pub fn fill(accounts: &mut HashMap<String, User>) {
+    let store = match STORE.get() {
+        Some(store) => store,
+        None => return,
+    };
+    let secrets = store.secrets.lock().unwrap();
+    for (alias, user) in accounts.iter_mut() {
+        if let Some(secret) = secrets.get(alias) {
+            user.link = user.link.take().or_else(|| secret.link.clone());
+            user.access_token = user
+                .access_token
+                .take()
+                .or_else(|| secret.access_token.clone());
+            user.cookie_token = user
+                .cookie_token
+                .take()
+                .or_else(|| secret.cookie_token.clone());
+        }
+    }
+}
+
+/// Move the secrets of `user` into the store, leaving only the non-secret settings.
+/// Call [`save`] afterwards.
+pub fn take(alias: &str, user: &mut User) {
+    let store = match STORE.get() {
+        Some(store) => store,
+        None => return,
+    };
+    let mut secrets = store.secrets.lock().unwrap();
+    let secret = secrets.entry(alias.to_string()).or_default();
+    if let Some(link) = user.link.take() {
+        secret.link = Some(link);
+    }
+    if let Some(token) = user.access_token.take() {
+        secret.access_token = Some(token);
+    }
+    if let Some(token) = user.cookie_token.take() {
+        secret.cookie_token = Some(token);
+    }
+}
+
+/// Keep new tokens of `alias` in the store. Call [`save`] afterwards.
+pub fn set_tokens(alias: &str, access_token: &str, cookie_token: &str) {
+    if let Some(store) = STORE.get() {
+        let mut secrets = store.secrets.lock().unwrap();
+        let secret = secrets.entry(alias.to_string()).or_default();
+        secret.access_token = Some(access_token.to_string());
+        secret.cookie_token = Some(cookie_token.to_string());
+    }
+}
+
+/// The cookie jar of `alias` kept in the store.
+pub fn cookies(alias: &str) -> Option<String> {
+    let store = STORE.get()?;
+    let secrets = store.secrets.lock().unwrap();
+    secrets.get(alias)?.cookies.clone()
+}
+
+/// Keep the cookie jar of `alias` in the store, or forget it. Call [`save`] afterwards.
+pub fn set_cookies(alias: &str, jar: Option<String>) {
+    if let Some(store) = STORE.get() {
+        let mut secrets = store.secrets.lock().unwrap();
+        secrets.entry(alias.to_string()).or_default().cookies = jar;
+    }
+}
+
+pub fn remove(alias: &str) {
+    if let Some(store) = STORE.get() {
+        store.secrets.lock().unwrap().remove(alias);
+    }
+}
+
+/// Encrypt the store back to disk. Only called by `config` while it holds its write lock.
+pub fn save() -> Result<(), String> {
+    let store = match STORE.get() {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+    let plaintext =
+        serde_json::to_vec(&*store.secrets.lock().unwrap()).expect("Unable to serialize secrets");
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let data = Aes256Gcm::new(&store.key)
+        .encrypt(&nonce, plaintext.as_ref())
+        .map_err(|err| err.to_string())?;
+    let file = StoreFile {
+        kdf: "argon2id".to_string(),
+        salt: STANDARD.encode(store.salt),
+        nonce: STANDARD.encode(nonce),
+        data: STANDARD.encode(data),
+    };
+    let json = serde_json::to_string_pretty(&file).expect("Unable to serialize secrets");
+    crate::config::write_atomic(&store.path, json.as_bytes())
+        .map_err(|err| format!("secrets {:?}: {}", store.path, err))
+}
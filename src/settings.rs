@@ -0,0 +1,209 @@
+use crate::config::Global;
+use crate::logging::LogFormat;
+use crate::notify;
+use chrono::NaiveTime;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Process-wide settings, read once at startup.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// local time at which the daily summary is written
+    pub summary_time: NaiveTime,
+    pub reports_dir: PathBuf,
+    pub log_format: LogFormat,
+    /// write one log file per account into this directory when set
+    pub log_dir: Option<PathBuf>,
+    /// rotate a log file once it grows past this many bytes
+    pub log_max_size: u64,
+    /// number of rotated files kept per log
+    pub log_keep: usize,
+    /// serve Prometheus metrics on this address when set
+    pub metrics_addr: Option<SocketAddr>,
+    /// serve the status dashboard on this address when set
+    pub dashboard_addr: Option<SocketAddr>,
+    /// serve the control API on this address when set
+    pub control_addr: Option<SocketAddr>,
+    /// required as `Authorization: Bearer <token>` by the control API when set
+    pub control_token: Option<String>,
+    /// post notifications as JSON to this URL when set
+    pub webhook_url: Option<String>,
+    /// notifications posted to the webhook, all of them when empty
+    pub webhook_events: Vec<notify::Kind>,
+    pub webhook_batch_secs: u64,
+    /// minimum seconds between two webhook posts
+    pub webhook_min_interval_secs: u64,
+    /// an account only getting errors for this long is reported as failing
+    pub failing_minutes: u64,
+    /// harvests with one of these ripeness values are reported
+    pub rare_ripeness: Vec<String>,
+    /// send notifications through this Telegram bot when set, together with `telegram_chat_id`
+    pub telegram_token: Option<String>,
+    pub telegram_chat_id: Option<String>,
+    pub telegram_api_base: String,
+    /// notifications sent to Telegram, all of them when empty
+    pub telegram_events: Vec<notify::Kind>,
+    pub telegram_templates: HashMap<notify::Kind, String>,
+    /// route the banana API requests through this proxy, unless the account sets its own
+    pub proxy: Option<String>,
+    /// keep links and tokens in this encrypted file instead of the config
+    pub secrets_file: Option<PathBuf>,
+    /// previous versions of the config kept as `{config}.bak.{n}` when it is rewritten
+    pub config_backups: usize,
+    /// check the config for changes this often and reload the accounts, never when 0
+    pub config_watch_secs: u64,
+    /// age after which the initData of a link is likely to be refused, only warned about
+    pub link_max_age_hours: i64,
+    /// warn this long before a link reaches `link_max_age_hours`
+    pub link_warn_hours: i64,
+    /// log in again this long before the access token expires
+    pub token_refresh_mins: i64,
+    /// one cookie jar per account, `{account}.json`
+    pub cookies_dir: PathBuf,
+    /// full-screen terminal UI instead of the scrolling log
+    pub tui: bool,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+fn env(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.is_empty())
+}
+
+/// The `global` config key of a `BANANA_*` variable.
+fn global_key(key: &str) -> String {
+    key.strip_prefix("BANANA_")
+        .unwrap_or(key)
+        .to_ascii_lowercase()
+}
+
+/// A `global` config value as if it was set in the environment: `BANANA_LOG_FORMAT` is
+/// read from `log_format`, lists are joined with commas.
+fn global_value(global: &Global, key: &str) -> Option<String> {
+    let name = global_key(key);
+    let plain = |value: &Value| match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    };
+    match global.get(&name)? {
+        Value::Null => None,
+        Value::Array(items) => Some(items.iter().map(plain).collect::<Vec<_>>().join(",")),
+        value => Some(plain(value)),
+    }
+}
+
+impl Settings {
+    pub fn from_env() -> Self {
+        Self::load(&Global::new())
+    }
+
+    /// Environment variables first, then the config's `global` section, then the defaults.
+    pub fn load(global: &Global) -> Self {
+        Self::read(global, &RefCell::new(vec![]))
+    }
+
+    /// Every key the `global` section can set.
+    pub fn keys() -> Vec<String> {
+        let keys = RefCell::new(vec![]);
+        Self::read(&Global::new(), &keys);
+        keys.into_inner()
+    }
+
+    fn read(global: &Global, keys: &RefCell<Vec<String>>) -> Self {
+        let current_dir = std::env::current_dir().unwrap();
+        let env = |key: &str| {
+            keys.borrow_mut().push(global_key(key));
+            env(key).or_else(|| global_value(global, key))
+        };
+        Self {
+            summary_time: env("BANANA_SUMMARY_TIME")
+                .and_then(|v| NaiveTime::parse_from_str(&v, "%H:%M").ok())
+                .unwrap_or_else(|| NaiveTime::from_hms_opt(23, 59, 0).unwrap()),
+            reports_dir: env("BANANA_REPORTS_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| current_dir.join("reports")),
+            log_format: env("BANANA_LOG_FORMAT")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_default(),
+            log_dir: env("BANANA_LOG_DIR").map(PathBuf::from),
+            log_max_size: env("BANANA_LOG_MAX_SIZE")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10 * 1024 * 1024),
+            log_keep: env("BANANA_LOG_KEEP")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(7),
+            metrics_addr: env("BANANA_METRICS_ADDR").and_then(|v| v.parse().ok()),
+            dashboard_addr: env("BANANA_DASHBOARD_ADDR").and_then(|v| v.parse().ok()),
+            control_addr: env("BANANA_CONTROL_ADDR").and_then(|v| v.parse().ok()),
+            control_token: env("BANANA_CONTROL_TOKEN"),
+            webhook_url: env("BANANA_WEBHOOK_URL"),
+            webhook_events: env("BANANA_WEBHOOK_EVENTS")
+                .map(|v| v.split(',').filter_map(|k| k.parse().ok()).collect())
+                .unwrap_or_default(),
+            webhook_batch_secs: env("BANANA_WEBHOOK_BATCH_SECS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+            webhook_min_interval_secs: env("BANANA_WEBHOOK_MIN_INTERVAL")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60),
+            failing_minutes: env("BANANA_FAILING_MINUTES")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            rare_ripeness: env("BANANA_RARE_RIPENESS")
+                .unwrap_or_else(|| "rare,epic,legendary".to_string())
+                .split(',')
+                .map(|r| r.trim().to_string())
+                .filter(|r| !r.is_empty())
+                .collect(),
+            telegram_token: env("BANANA_TELEGRAM_TOKEN"),
+            telegram_chat_id: env("BANANA_TELEGRAM_CHAT_ID"),
+            telegram_api_base: env("BANANA_TELEGRAM_API_BASE")
+                .unwrap_or_else(|| "https://api.telegram.org".to_string()),
+            telegram_events: env("BANANA_TELEGRAM_EVENTS")
+                .map(|v| v.split(',').filter_map(|k| k.parse().ok()).collect())
+                .unwrap_or_default(),
+            telegram_templates: notify::Kind::ALL
+                .into_iter()
+                .filter_map(|kind| {
+                    let key = format!("BANANA_TELEGRAM_TEMPLATE_{}", kind.name().to_uppercase());
+                    env(&key).map(|template| (kind, template.replace("\\n", "\n")))
+                })
+                .collect(),
+            proxy: env("BANANA_PROXY"),
+            secrets_file: env("BANANA_SECRETS_FILE").map(PathBuf::from),
+            config_backups: env("BANANA_CONFIG_BACKUPS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            config_watch_secs: env("BANANA_CONFIG_WATCH_SECS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
+            link_max_age_hours: env("BANANA_LINK_MAX_AGE_HOURS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(24),
+            link_warn_hours: env("BANANA_LINK_WARN_HOURS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            token_refresh_mins: env("BANANA_TOKEN_REFRESH_MINS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+            cookies_dir: env("BANANA_COOKIES_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| current_dir.join("cookies")),
+            tui: env("BANANA_TUI").is_some_and(|v| v == "1" || v == "true"),
+        }
+    }
+}
+
+pub fn init(settings: Settings) {
+    SETTINGS
+        .set(settings)
+        .expect("settings already initialized");
+}
+
+pub fn get() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::from_env)
+}
@@ -0,0 +1,194 @@
+use crate::events::Event;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::sync::{watch, Notify};
+use tokio::time::{sleep_until, Instant};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    #[default]
+    Starting,
+    LoggingIn,
+    Running,
+    Paused,
+    Failed,
+    /// the initData of its link is too old to log in, a new link has to be pasted
+    NeedsNewLink,
+}
+
+/// What the bot currently knows about one account, kept up to date from [`Event`]s.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct AccountState {
+    pub status: Status,
+    pub peel: Option<f64>,
+    pub usdt: Option<f64>,
+    pub today_click_count: i32,
+    pub max_click_count: i32,
+    pub remain_lottery_count: i32,
+    /// timestamp (ms) of the next claim
+    pub next_claim: Option<i64>,
+    /// timestamp (ms) at which the access token expires, when it is a JWT
+    pub token_expires: Option<i64>,
+    /// progress towards the account's goal, when it has one
+    pub goal: Option<String>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<i64>,
+    pub last_action: Option<String>,
+}
+
+/// An action requested from outside of the account's task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Click,
+    Claim,
+    Lottery,
+    Quests,
+}
+
+/// Runtime switches of one account, shared between its task and the UIs.
+pub struct Control {
+    paused: watch::Sender<bool>,
+    commands: Mutex<VecDeque<Command>>,
+    notify: Notify,
+}
+
+impl Control {
+    fn new() -> Self {
+        Self {
+            paused: watch::channel(false).0,
+            commands: Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Wait until the account is not paused.
+    pub async fn wait_resumed(&self) {
+        let mut rx = self.paused.subscribe();
+        let _ = rx.wait_for(|paused| !paused).await;
+    }
+
+    fn send(&self, command: Command) {
+        self.commands.lock().unwrap().push_back(command);
+        self.notify.notify_one();
+    }
+
+    /// The next requested command, or `None` once `deadline` is reached.
+    pub async fn next_command(&self, deadline: Instant) -> Option<Command> {
+        loop {
+            if let Some(command) = self.commands.lock().unwrap().pop_front() {
+                return Some(command);
+            }
+            tokio::select! {
+                _ = sleep_until(deadline) => return None,
+                _ = self.notify.notified() => {}
+            }
+        }
+    }
+}
+
+static ACCOUNTS: LazyLock<Mutex<BTreeMap<String, AccountState>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+static CONTROLS: LazyLock<Mutex<HashMap<String, Arc<Control>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn update(account: &str, f: impl FnOnce(&mut AccountState)) {
+    let mut accounts = ACCOUNTS.lock().unwrap();
+    f(accounts.entry(account.to_string()).or_default());
+}
+
+pub fn register(account: &str) {
+    update(account, |_| {});
+}
+
+pub fn set_status(account: &str, status: Status) {
+    update(account, |s| s.status = status);
+}
+
+pub fn status(account: &str) -> Option<Status> {
+    ACCOUNTS.lock().unwrap().get(account).map(|s| s.status)
+}
+
+pub fn set_token_expires(account: &str, timestamp: Option<i64>) {
+    update(account, |s| s.token_expires = timestamp);
+}
+
+pub fn set_goal(account: &str, progress: String) {
+    update(account, |s| s.goal = Some(progress));
+}
+
+/// Mark the account as failed, unless it already says it needs a new link.
+pub fn set_failed(account: &str) {
+    update(account, |s| {
+        if s.status != Status::NeedsNewLink {
+            s.status = Status::Failed;
+        }
+    });
+}
+
+pub fn set_last_action(account: &str, action: &str) {
+    update(account, |s| s.last_action = Some(action.to_string()));
+}
+
+pub fn control(account: &str) -> Arc<Control> {
+    CONTROLS
+        .lock()
+        .unwrap()
+        .entry(account.to_string())
+        .or_insert_with(|| Arc::new(Control::new()))
+        .clone()
+}
+
+pub fn pause(account: &str) {
+    control(account).paused.send_replace(true);
+    set_status(account, Status::Paused);
+    crate::utils::format_println(account, "paused");
+}
+
+pub fn resume(account: &str) {
+    control(account).paused.send_replace(false);
+    set_status(account, Status::Running);
+    crate::utils::format_println(account, "resumed");
+}
+
+/// Queue `command` for the account; it runs as soon as the account's task is idle and not paused.
+pub fn send_command(account: &str, command: Command) {
+    control(account).send(command);
+    crate::utils::format_println(account, &format!("{:?} requested", command));
+}
+
+/// Forget an account that was removed from the config.
+pub fn remove(account: &str) {
+    ACCOUNTS.lock().unwrap().remove(account);
+    CONTROLS.lock().unwrap().remove(account);
+}
+
+pub fn snapshot() -> BTreeMap<String, AccountState> {
+    ACCOUNTS.lock().unwrap().clone()
+}
+
+pub fn on_event(account: &str, event: &Event) {
+    update(account, |s| match event {
+        Event::UserInfo(info) => {
+            s.peel = Some(info.peel);
+            s.usdt = Some(info.usdt);
+            s.today_click_count = info.today_click_count;
+            s.max_click_count = info.max_click_count;
+            s.remain_lottery_count = info.lottery_info.remain_lottery_count;
+        }
+        Event::ClickLimit(max) => s.max_click_count = *max,
+        Event::Clicked(count) => s.today_click_count += count,
+        Event::Harvested { .. } => s.remain_lottery_count = (s.remain_lottery_count - 1).max(0),
+        Event::NextClaim(timestamp) => s.next_claim = Some(*timestamp),
+        Event::Error(msg) => {
+            s.last_error = Some(msg.to_string());
+            s.last_error_at = Some(crate::utils::get_current_timestamp());
+        }
+        _ => {}
+    });
+}
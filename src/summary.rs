@@ -0,0 +1,243 @@
+use crate::events::Event;
+use crate::notify;
+use chrono::{Local, NaiveTime};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tokio::time::sleep;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Harvest {
+    pub name: String,
+    pub ripeness: String,
+}
+
+/// What an account did since the last summary.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct DailyStats {
+    pub clicks: i64,
+    pub max_click_count: i32,
+    pub claims: u32,
+    pub speedups: u32,
+    pub harvests: Vec<Harvest>,
+    pub quests_claimed: u32,
+    pub ads_income: f64,
+    pub peel_start: Option<f64>,
+    pub peel_end: Option<f64>,
+    pub usdt_start: Option<f64>,
+    pub usdt_end: Option<f64>,
+    pub errors: Vec<String>,
+}
+
+impl DailyStats {
+    pub fn update_balance(&mut self, peel: f64, usdt: f64) {
+        self.peel_start.get_or_insert(peel);
+        self.usdt_start.get_or_insert(usdt);
+        self.peel_end = Some(peel);
+        self.usdt_end = Some(usdt);
+    }
+
+    pub fn peel_delta(&self) -> f64 {
+        self.peel_end.unwrap_or_default() - self.peel_start.unwrap_or_default()
+    }
+
+    pub fn usdt_delta(&self) -> f64 {
+        self.usdt_end.unwrap_or_default() - self.usdt_start.unwrap_or_default()
+    }
+
+    /// Counters for a new day; the last known balance becomes the starting balance.
+    fn next_day(&self) -> Self {
+        Self {
+            max_click_count: self.max_click_count,
+            peel_start: self.peel_end,
+            peel_end: self.peel_end,
+            usdt_start: self.usdt_end,
+            usdt_end: self.usdt_end,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Summary {
+    pub date: String,
+    pub accounts: BTreeMap<String, DailyStats>,
+}
+
+static STATS: LazyLock<Mutex<BTreeMap<String, DailyStats>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+fn record(name: &str, f: impl FnOnce(&mut DailyStats)) {
+    let mut stats = STATS.lock().unwrap();
+    f(stats.entry(name.to_string()).or_default());
+}
+
+pub fn on_event(account: &str, event: &Event) {
+    record(account, |s| match event {
+        Event::ClickLimit(max) => s.max_click_count = *max,
+        Event::Clicked(count) => s.clicks += *count as i64,
+        Event::Claimed => s.claims += 1,
+        Event::SpeedUp => s.speedups += 1,
+        Event::Harvested { name, ripeness } => s.harvests.push(Harvest {
+            name: name.to_string(),
+            ripeness: ripeness.to_string(),
+        }),
+        Event::QuestClaimed => s.quests_claimed += 1,
+        Event::AdsIncome(income) => s.ads_income += income,
+        Event::UserInfo(info) => s.update_balance(info.peel, info.usdt),
+        Event::Error(msg) => s.errors.push(format!("[{}] {}", crate::utils::now(), msg)),
+        Event::NextClaim(_) | Event::Retry { .. } => {}
+    });
+}
+
+/// Take the stats collected so far and start counting a new day.
+pub fn take(date: &str) -> Summary {
+    let mut stats = STATS.lock().unwrap();
+    let accounts = stats.clone();
+    for s in stats.values_mut() {
+        *s = s.next_day();
+    }
+
+    Summary {
+        date: date.to_string(),
+        accounts,
+    }
+}
+
+impl Summary {
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let _ = writeln!(md, "# Banana daily summary {}\n", self.date);
+        let _ = writeln!(
+            md,
+            "| account | clicks | claims | speedups | harvests | quests | ads income | peel Δ | usdt Δ | errors |"
+        );
+        let _ = writeln!(md, "|---|---|---|---|---|---|---|---|---|---|");
+
+        let mut total = DailyStats::default();
+        let (mut peel_delta, mut usdt_delta) = (0f64, 0f64);
+        for (name, s) in &self.accounts {
+            let _ = writeln!(
+                md,
+                "| {} | {}/{} | {} | {} | {} | {} | {:.2} | {:+.2} | {:+.4} | {} |",
+                name,
+                s.clicks,
+                s.max_click_count,
+                s.claims,
+                s.speedups,
+                s.harvests.len(),
+                s.quests_claimed,
+                s.ads_income,
+                s.peel_delta(),
+                s.usdt_delta(),
+                s.errors.len()
+            );
+            total.clicks += s.clicks;
+            total.max_click_count += s.max_click_count;
+            total.claims += s.claims;
+            total.speedups += s.speedups;
+            total.quests_claimed += s.quests_claimed;
+            total.ads_income += s.ads_income;
+            peel_delta += s.peel_delta();
+            usdt_delta += s.usdt_delta();
+        }
+        let _ = writeln!(
+            md,
+            "| **total** | {}/{} | {} | {} | {} | {} | {:.2} | {:+.2} | {:+.4} | {} |",
+            total.clicks,
+            total.max_click_count,
+            total.claims,
+            total.speedups,
+            self.accounts
+                .values()
+                .map(|s| s.harvests.len())
+                .sum::<usize>(),
+            total.quests_claimed,
+            total.ads_income,
+            peel_delta,
+            usdt_delta,
+            self.accounts
+                .values()
+                .map(|s| s.errors.len())
+                .sum::<usize>()
+        );
+
+        for (name, s) in &self.accounts {
+            if s.harvests.is_empty() && s.errors.is_empty() {
+                continue;
+            }
+            let _ = writeln!(md, "\n## {}", name);
+            if !s.harvests.is_empty() {
+                let _ = writeln!(md, "\nharvests:");
+                for h in &s.harvests {
+                    let _ = writeln!(md, "- {} ({})", h.name, h.ripeness);
+                }
+            }
+            if !s.errors.is_empty() {
+                let _ = writeln!(md, "\nerrors:");
+                for err in &s.errors {
+                    let _ = writeln!(md, "- {}", err);
+                }
+            }
+        }
+
+        md
+    }
+
+    /// A few lines per account, short enough for a chat message.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("Banana daily summary {}\n", self.date);
+        for (name, s) in &self.accounts {
+            let _ = write!(
+                text,
+                "\n{}: peel {:+.2}, usdt {:+.4}, {} harvests, {} claims",
+                name,
+                s.peel_delta(),
+                s.usdt_delta(),
+                s.harvests.len(),
+                s.claims
+            );
+            if !s.errors.is_empty() {
+                let _ = write!(text, ", {} errors", s.errors.len());
+            }
+        }
+        text
+    }
+
+    pub fn write(&self, dir: &Path) -> std::io::Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(format!("{}.md", self.date)), self.to_markdown())?;
+        fs::write(
+            dir.join(format!("{}.json", self.date)),
+            serde_json::to_string_pretty(self).expect("Unable to serialize summary"),
+        )
+    }
+}
+
+fn until_next(time: NaiveTime) -> Duration {
+    let now = Local::now().naive_local();
+    let mut next = now.date().and_time(time);
+    if next <= now {
+        next += chrono::Duration::days(1);
+    }
+    (next - now).to_std().unwrap_or_default()
+}
+
+/// Write a summary of the day at `time` (local) every day.
+pub async fn run_scheduler(time: NaiveTime, dir: &Path) {
+    loop {
+        sleep(until_next(time)).await;
+        let summary = take(&Local::now().format("%F").to_string());
+        match summary.write(dir) {
+            Ok(()) => log::info!("daily summary written to {:?}", dir),
+            Err(err) => log::error!("write daily summary err: {:?}", err),
+        }
+        notify::broadcast(notify::Kind::DailySummary, &summary.to_text());
+        // make sure we don't fire twice within the same minute
+        sleep(Duration::from_secs(60)).await;
+    }
+}
@@ -0,0 +1,83 @@
+use crate::notify::{self, Kind, Notification};
+use chrono::{Local, TimeZone};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::sleep;
+
+// Telegram allows about one message per second in a chat
+const SEND_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct Telegram {
+    /// e.g. `https://api.telegram.org`
+    pub api_base: String,
+    pub token: String,
+    pub chat_id: String,
+    /// kinds to deliver, all of them when empty
+    pub events: Vec<Kind>,
+    /// overrides of [`default_template`]
+    pub templates: HashMap<Kind, String>,
+}
+
+fn default_template(kind: Kind) -> &'static str {
+    match kind {
+        Kind::AuthFailure => "🔑 [{account}] auth failure: {message}",
+        Kind::NeedsNewLink => "🔗 [{account}] {message}",
+        Kind::RareHarvest => "🍌 [{account}] {message}",
+        Kind::UnknownQuest => "❓ [{account}] {message}",
+        Kind::AccountFailing => "🚨 [{account}] {message}",
+        Kind::DailySummary => "📊 {message}",
+    }
+}
+
+/// Fill `{account}`, `{kind}`, `{message}` and `{time}` in `template`.
+fn render(template: &str, n: &Notification) -> String {
+    let time = Local
+        .timestamp_millis_opt(n.timestamp)
+        .single()
+        .map(|t| t.format("%F %T").to_string())
+        .unwrap_or_default();
+    template
+        .replace("{account}", n.account.as_deref().unwrap_or("all"))
+        .replace("{kind}", n.kind.name())
+        .replace("{message}", &n.message)
+        .replace("{time}", &time)
+}
+
+async fn send_message(client: &reqwest::Client, telegram: &Telegram, text: &str) {
+    let url = format!(
+        "{}/bot{}/sendMessage",
+        telegram.api_base.trim_end_matches('/'),
+        telegram.token
+    );
+    let body = json!({ "chat_id": telegram.chat_id, "text": text });
+    match client.post(&url).json(&body).send().await {
+        Ok(response) if response.status().is_success() => {}
+        // never log the url, it contains the bot token
+        Ok(response) => log::error!("telegram sendMessage status: {}", response.status()),
+        Err(err) => log::error!("telegram sendMessage err: {:?}", err.without_url()),
+    }
+}
+
+async fn run(telegram: Telegram, mut rx: UnboundedReceiver<Notification>) {
+    let client = reqwest::Client::new();
+    while let Some(n) = rx.recv().await {
+        if !telegram.events.is_empty() && !telegram.events.contains(&n.kind) {
+            continue;
+        }
+        let template = telegram
+            .templates
+            .get(&n.kind)
+            .map(String::as_str)
+            .unwrap_or_else(|| default_template(n.kind));
+        send_message(&client, &telegram, &render(template, &n)).await;
+        sleep(SEND_INTERVAL).await;
+    }
+}
+
+/// Deliver notifications through a Telegram bot in the background.
+pub fn spawn(telegram: Telegram) {
+    let rx = notify::subscribe();
+    tokio::spawn(run(telegram, rx));
+}
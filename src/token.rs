@@ -0,0 +1,44 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+
+/// The claims of an access token we care about, in unix seconds.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Claims {
+    pub exp: Option<i64>,
+    pub iat: Option<i64>,
+}
+
+impl Claims {
+    /// Seconds left before the token expires, negative once it has.
+    pub fn expires_in(&self, now: i64) -> Option<i64> {
+        self.exp.map(|exp| exp - now)
+    }
+
+    /// Seconds since the token was issued.
+    pub fn age(&self, now: i64) -> Option<i64> {
+        self.iat.map(|iat| now - iat)
+    }
+}
+
+/// Decode the claims of `token`, `None` if it isn't a JWT. The signature isn't checked, only
+/// the server can.
+pub fn claims(token: &str) -> Option<Claims> {
+    let parts: Vec<&str> = token.split('.').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let header: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[0]).ok()?).ok()?;
+    header.get("alg")?;
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[1]).ok()?).ok()
+}
+
+/// `3h12m` for a duration in seconds.
+pub fn format_duration(secs: i64) -> String {
+    let sign = if secs < 0 { "-" } else { "" };
+    let secs = secs.abs();
+    match secs {
+        s if s >= 3600 => format!("{}{}h{:02}m", sign, s / 3600, s / 60 % 60),
+        s => format!("{}{}m", sign, s / 60),
+    }
+}
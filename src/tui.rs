@@ -0,0 +1,163 @@
+use crate::logging;
+use crate::state::{self, AccountState, Command, Status};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::time::Duration;
+
+// how often the screen is redrawn when no key is pressed
+const TICK: Duration = Duration::from_millis(500);
+
+fn countdown(next_claim: Option<i64>, now: i64) -> String {
+    match next_claim {
+        Some(t) => {
+            let secs = (t - now).max(0) / 1000;
+            format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+        }
+        None => "-".to_string(),
+    }
+}
+
+fn status_style(status: Status) -> Style {
+    match status {
+        Status::Running => Style::default().fg(Color::Green),
+        Status::Failed => Style::default().fg(Color::Red),
+        Status::Paused => Style::default().fg(Color::DarkGray),
+        Status::NeedsNewLink => Style::default().fg(Color::Magenta),
+        _ => Style::default().fg(Color::Yellow),
+    }
+}
+
+fn account_row<'a>(name: &'a str, s: &'a AccountState, now: i64) -> Row<'a> {
+    let balance = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.2}", v));
+    Row::new(vec![
+        Line::from(name),
+        Line::styled(format!("{:?}", s.status), status_style(s.status)),
+        Line::from(countdown(s.next_claim, now)),
+        Line::from(format!("{}/{}", s.today_click_count, s.max_click_count)),
+        Line::from(s.remain_lottery_count.to_string()),
+        Line::from(balance(s.peel)),
+        Line::from(balance(s.usdt)),
+        Line::from(s.last_action.clone().unwrap_or_default()),
+    ])
+}
+
+fn draw(frame: &mut Frame, table_state: &mut TableState) {
+    let accounts = state::snapshot();
+    let now = crate::utils::get_current_timestamp();
+    let [top, bottom, help] = Layout::vertical([
+        Constraint::Length(accounts.len() as u16 + 3),
+        Constraint::Min(5),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let rows: Vec<Row> = accounts
+        .iter()
+        .map(|(name, s)| account_row(name, s, now))
+        .collect();
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(16),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Length(12),
+            Constraint::Length(10),
+            Constraint::Min(20),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "account",
+            "status",
+            "claim in",
+            "clicks",
+            "lottery",
+            "peel",
+            "usdt",
+            "last action",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+    .block(Block::bordered().title(" Banana Bot 🍌 "));
+    frame.render_stateful_widget(table, top, table_state);
+
+    let selected = table_state
+        .selected()
+        .and_then(|i| accounts.keys().nth(i))
+        .cloned()
+        .unwrap_or_default();
+    let height = bottom.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = logging::recent(&selected, height)
+        .into_iter()
+        .map(Line::from)
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(format!(" log: {} ", selected))),
+        bottom,
+    );
+
+    frame.render_widget(
+        Paragraph::new("↑/↓ select  p pause/resume  c claim now  q quit"),
+        help,
+    );
+}
+
+fn selected_account(table_state: &TableState) -> Option<String> {
+    table_state
+        .selected()
+        .and_then(|i| state::snapshot().into_keys().nth(i))
+}
+
+fn event_loop(terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+    let mut table_state = TableState::default().with_selected(0);
+    loop {
+        terminal.draw(|frame| draw(frame, &mut table_state))?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Down | KeyCode::Char('j') => table_state.select_next(),
+            KeyCode::Up | KeyCode::Char('k') => table_state.select_previous(),
+            KeyCode::Char('p') => {
+                if let Some(account) = selected_account(&table_state) {
+                    if state::control(&account).is_paused() {
+                        state::resume(&account);
+                    } else {
+                        state::pause(&account);
+                    }
+                }
+            }
+            KeyCode::Char('c') => {
+                if let Some(account) = selected_account(&table_state) {
+                    state::send_command(&account, Command::Claim);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Run the terminal UI until the user quits, then exit the process.
+pub fn run() -> ! {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal);
+    ratatui::restore();
+    if let Err(err) = result {
+        eprintln!("tui err: {:?}", err);
+    }
+    std::process::exit(0)
+}
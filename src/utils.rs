@@ -1,15 +1,14 @@
+use crate::goal::Goal;
+use crate::logging;
 use aes_gcm::{
     aead::{Aead, AeadCore, KeyInit, OsRng},
     Aes256Gcm, Key,
 };
+use base64::{engine::general_purpose::STANDARD, Engine};
 use chrono::Local;
-use log::{error, info};
+use log::Level;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
-use std::io::Write;
 use std::time::{SystemTime, UNIX_EPOCH};
-use base64::{engine::general_purpose::STANDARD, Engine};
 
 use reqwest::header::{
     HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, CACHE_CONTROL, CONTENT_TYPE, ORIGIN, PRAGMA,
@@ -30,10 +29,14 @@ pub fn get_current_timestamp() -> i64 {
 }
 
 pub fn format_println(name: &str, msg: &str) {
-    info!("[{}] [{}]: {}", now(), name, msg);
+    logging::message(Level::Info, name, msg);
+}
+pub fn format_warn(name: &str, msg: &str) {
+    logging::message(Level::Warn, name, msg);
 }
 pub fn format_error(name: &str, msg: &str) {
-    error!("[{}] [{}]: {}", now(), name, msg);
+    logging::message(Level::Error, name, msg);
+    crate::events::emit(name, crate::events::Event::Error(msg));
 }
 
 fn aes_gcm_encrypt(plaintext: &str, key_str: &str) -> String {
@@ -49,7 +52,9 @@ fn aes_gcm_encrypt(plaintext: &str, key_str: &str) -> String {
     let nonce = Aes256Gcm::generate_nonce(&mut OsRng); // 96-bits; unique per message
 
     // 加密数据
-    let ciphertext = cipher.encrypt(&nonce, plaintext.as_bytes().as_ref()).unwrap();
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes().as_ref())
+        .unwrap();
 
     // 将 nonce 和密文连接起来，并进行 Base64 编码
     let mut result = nonce.to_vec();
@@ -85,7 +90,10 @@ pub fn init_headers(h: &mut HeaderMap) -> &mut HeaderMap {
         .duration_since(UNIX_EPOCH)
         .expect("Time went backwards");
     let now = since_the_epoch.as_millis().to_string();
-    h.insert("request-time", HeaderValue::from_str(&aes_gcm_encrypt(&now, "1,1,0")).unwrap());
+    h.insert(
+        "request-time",
+        HeaderValue::from_str(&aes_gcm_encrypt(&now, "1,1,0")).unwrap(),
+    );
     h.insert(
         REFERRER_POLICY,
         HeaderValue::from_static("strict-origin-when-cross-origin"),
@@ -95,12 +103,86 @@ pub fn init_headers(h: &mut HeaderMap) -> &mut HeaderMap {
     h
 }
 
+fn enabled() -> bool {
+    true
+}
+
+fn is_enabled(value: &bool) -> bool {
+    *value
+}
+
+/// Parts of the routine an account runs, everything is on unless switched off in its config.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Features {
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub click: bool,
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub quests: bool,
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub quest_lottery: bool,
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub claim: bool,
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub speedup: bool,
+    /// harvest bananas with the lottery tickets
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub lottery: bool,
+    /// share every harvested banana
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub share: bool,
+    /// claim the ads income after clicking, speeding up and harvesting
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub ads: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            click: true,
+            quests: true,
+            quest_lottery: true,
+            claim: true,
+            speedup: true,
+            lottery: true,
+            share: true,
+            ads: true,
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct User {
     pub link: Option<String>,
     pub access_token: Option<String>,
     pub cookie_token: Option<String>,
     pub invite_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goal: Option<Goal>,
+    /// overrides the global `proxy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(flatten)]
+    pub features: Features,
+}
+
+impl User {
+    /// Keys of an account in the config, anything else in it is left alone.
+    pub const FIELDS: &'static [&'static str] = &[
+        "link",
+        "access_token",
+        "cookie_token",
+        "invite_code",
+        "goal",
+        "proxy",
+        "click",
+        "quests",
+        "quest_lottery",
+        "claim",
+        "speedup",
+        "lottery",
+        "share",
+        "ads",
+    ];
 }
 
 impl Clone for User {
@@ -110,21 +192,9 @@ impl Clone for User {
             access_token: self.access_token.clone(),
             cookie_token: self.cookie_token.clone(),
             invite_code: self.invite_code.clone(),
+            goal: self.goal.clone(),
+            proxy: self.proxy.clone(),
+            features: self.features.clone(),
         }
     }
 }
-
-pub fn read_config_json(file_path: &str) -> HashMap<String, User> {
-    let file = fs::File::open(file_path).unwrap();
-    let reader = std::io::BufReader::new(file);
-    let hashmap: HashMap<String, User> =
-        serde_json::from_reader(reader).expect("Unable to parse JSON");
-    hashmap
-}
-
-pub fn write_config_json(file_path: &str, data: &HashMap<String, User>) {
-    let json_data = serde_json::to_string_pretty(data).expect("Unable to serialize data");
-    let mut file = fs::File::create(file_path).expect("Unable to create file");
-    file.write_all(json_data.as_bytes())
-        .expect("Unable to write data to file");
-}
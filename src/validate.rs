@@ -0,0 +1,370 @@
+use crate::config::{self, Source};
+use crate::init_data;
+use crate::secrets;
+use crate::settings::{self, Settings};
+use crate::token;
+use crate::utils::User;
+use colored::*;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+const COOKIE_NAME: &str = "banana-game:user:token";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Error,
+    Warning,
+}
+
+struct Diagnostic {
+    file: PathBuf,
+    line: Option<usize>,
+    level: Level,
+    account: Option<String>,
+    message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        match self.level {
+            Level::Error => write!(f, ": {}: ", "error".red())?,
+            Level::Warning => write!(f, ": {}: ", "warning".yellow())?,
+        }
+        if let Some(account) = &self.account {
+            write!(f, "[{}] ", account)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Whether `line` starts the definition of `key`: `"key":`, `key =`, `key:` or a
+/// `[accounts.key]` table header.
+fn defines(line: &str, key: &str) -> bool {
+    let line = line.trim_start().trim_start_matches('[');
+    let line = line.strip_prefix("accounts.").unwrap_or(line);
+    let line = line.trim_start_matches(['"', '\'']);
+    line.strip_prefix(key).is_some_and(|rest| {
+        rest.trim_start_matches(['"', '\''])
+            .trim_start()
+            .starts_with([':', '=', ']'])
+    })
+}
+
+/// 1-based line defining `key`, looking from line `from` on and before line `until` when
+/// set.
+fn line_of(content: &str, key: &str, from: usize, until: Option<usize>) -> Option<usize> {
+    content
+        .lines()
+        .enumerate()
+        .skip(from.saturating_sub(1))
+        .take_while(|(n, _)| until.is_none_or(|until| n + 1 < until))
+        .find(|(_, line)| defines(line, key))
+        .map(|(n, _)| n + 1)
+}
+
+/// Problems of one account, with the key they are about.
+type Findings = Vec<(Level, Option<String>, String)>;
+
+/// Check the `tgWebAppData` of a link, returns the Telegram user id it is for. An old link
+/// is only an error when the account has to log in with it soon, see `needs_login`.
+fn check_link(link: &str, needs_login: bool, now: i64, findings: &mut Findings) -> Option<i64> {
+    let mut push = |level, message| findings.push((level, Some("link".to_string()), message));
+    let init_data = match init_data::parse(link) {
+        Ok(link) => link.init_data,
+        Err(err) => {
+            push(Level::Error, err.to_string());
+            return None;
+        }
+    };
+
+    for field in init_data.missing() {
+        push(Level::Error, format!("tgWebAppData has no {} field", field));
+    }
+    if let Some(age) = init_data.auth_date.map(|date| now - date) {
+        let settings = settings::get();
+        let max_age = settings.link_max_age_hours * 3600;
+        if age >= max_age && needs_login {
+            push(
+                Level::Error,
+                format!(
+                    "tgWebAppData is {}h old and the account has to log in with it, it may be refused",
+                    age / 3600
+                ),
+            );
+        } else if age >= max_age {
+            push(
+                Level::Warning,
+                format!(
+                    "tgWebAppData is {}h old, paste a new one before the tokens expire",
+                    age / 3600
+                ),
+            );
+        } else if age >= max_age - settings.link_warn_hours * 3600 {
+            push(
+                Level::Warning,
+                format!("tgWebAppData expires in {}min", (max_age - age) / 60),
+            );
+        }
+    }
+    init_data.user.map(|user| user.id)
+}
+
+fn check_tokens(user: &User, now: i64, findings: &mut Findings) {
+    if let Some(token) = &user.access_token {
+        match token::claims(token) {
+            // opaque tokens are fine, only a broken JWT is reported
+            None if token.split('.').count() == 3 => findings.push((
+                Level::Error,
+                Some("access_token".into()),
+                "access_token looks like a JWT but can't be decoded, remove it to log in again"
+                    .into(),
+            )),
+            Some(claims) if claims.expires_in(now).is_some_and(|secs| secs <= 0) => {
+                findings.push((
+                    Level::Warning,
+                    Some("access_token".into()),
+                    "access_token has expired, the account logs in again when started".into(),
+                ))
+            }
+            _ => {}
+        }
+    }
+    if let Some(token) = &user.cookie_token {
+        let value = token
+            .strip_prefix(COOKIE_NAME)
+            .and_then(|v| v.strip_prefix('='));
+        if value.is_none_or(str::is_empty) {
+            findings.push((
+                Level::Error,
+                Some("cookie_token".into()),
+                format!("cookie_token should look like {}=...", COOKIE_NAME),
+            ));
+        }
+    }
+    if user.access_token.is_some() != user.cookie_token.is_some() {
+        findings.push((
+            Level::Warning,
+            None,
+            "only one of access_token and cookie_token is set, both are needed".into(),
+        ));
+    }
+}
+
+/// Check one account, returns the Telegram user id of its link.
+fn check_account(alias: &str, account: &Value, now: i64, findings: &mut Findings) -> Option<i64> {
+    let object = match account.as_object() {
+        Some(object) => object,
+        None => {
+            findings.push((Level::Error, None, "account is not a map".into()));
+            return None;
+        }
+    };
+    for key in object.keys() {
+        if !User::FIELDS.contains(&key.as_str()) {
+            findings.push((
+                Level::Warning,
+                Some(key.clone()),
+                format!("unknown key {:?} is ignored", key),
+            ));
+        }
+    }
+
+    let user: User = match serde_json::from_value(account.clone()) {
+        Ok(user) => user,
+        Err(err) => {
+            findings.push((Level::Error, None, err.to_string()));
+            return None;
+        }
+    };
+    // links and tokens may be in the secrets store
+    let mut accounts = HashMap::from([(alias.to_string(), user)]);
+    secrets::fill(&mut accounts);
+    let user = accounts.remove(alias).unwrap();
+
+    check_tokens(&user, now, findings);
+    // without tokens, or with a JWT about to be refreshed, the link is used right away
+    let needs_login = match &user.access_token {
+        None => true,
+        Some(token) => token::claims(token)
+            .and_then(|claims| claims.expires_in(now))
+            .is_some_and(|secs| secs <= settings::get().token_refresh_mins * 60),
+    };
+    match &user.link {
+        Some(link) => check_link(link, needs_login, now, findings),
+        None if user.access_token.is_none() => {
+            findings.push((
+                Level::Error,
+                Some("link".into()),
+                "no link and no tokens, the account can't log in".into(),
+            ));
+            None
+        }
+        None => {
+            findings.push((
+                Level::Warning,
+                Some("link".into()),
+                "no link, the account can't log in again once its tokens expire".into(),
+            ));
+            None
+        }
+    }
+}
+
+fn check_global(source: &Source, global: &Map<String, Value>, diagnostics: &mut Vec<Diagnostic>) {
+    let keys = Settings::keys();
+    let from = line_of(&source.content, "global", 1, None).unwrap_or(1);
+    for key in global.keys() {
+        if !keys.contains(key) {
+            diagnostics.push(Diagnostic {
+                file: source.file.clone(),
+                line: line_of(&source.content, key, from, None),
+                level: Level::Warning,
+                account: None,
+                message: format!("unknown global key {:?} is ignored", key),
+            });
+        }
+    }
+}
+
+/// Check the config at `path` and print what is wrong with it, returns false on errors.
+pub fn run(path: &Path) -> bool {
+    let sources = match config::sources(path) {
+        Ok(sources) => sources,
+        Err(err) => {
+            config::explain(&err);
+            return false;
+        }
+    };
+    let now = crate::utils::get_current_timestamp() / 1000;
+    let mut diagnostics = vec![];
+    let mut checked = 0;
+    // telegram user id -> aliases using it
+    let mut ids: BTreeMap<i64, Vec<(String, PathBuf, Option<usize>)>> = BTreeMap::new();
+
+    for source in &sources {
+        let value = match &source.value {
+            Ok(value) => value,
+            Err(err) => {
+                diagnostics.push(Diagnostic {
+                    file: source.file.clone(),
+                    line: None,
+                    level: Level::Error,
+                    account: None,
+                    message: err.clone(),
+                });
+                continue;
+            }
+        };
+        let root = match value.as_object() {
+            Some(root) => root,
+            None => {
+                diagnostics.push(Diagnostic {
+                    file: source.file.clone(),
+                    line: Some(1),
+                    level: Level::Error,
+                    account: None,
+                    message: "expected a map".into(),
+                });
+                continue;
+            }
+        };
+
+        // (alias, account, line of its definition)
+        let mut accounts: Vec<(String, &Value, usize)> = vec![];
+        match source.stem.as_deref() {
+            Some("global") => check_global(source, root, &mut diagnostics),
+            Some(alias) => accounts.push((alias.to_string(), value, 1)),
+            None if root.contains_key("accounts") || root.contains_key("global") => {
+                for key in root.keys() {
+                    if key != "accounts" && key != "global" {
+                        diagnostics.push(Diagnostic {
+                            file: source.file.clone(),
+                            line: line_of(&source.content, key, 1, None),
+                            level: Level::Warning,
+                            account: None,
+                            message: format!("unknown section {:?} is ignored", key),
+                        });
+                    }
+                }
+                if let Some(global) = root.get("global").and_then(Value::as_object) {
+                    check_global(source, global, &mut diagnostics);
+                }
+                let from = line_of(&source.content, "accounts", 1, None).unwrap_or(1);
+                if let Some(map) = root.get("accounts").and_then(Value::as_object) {
+                    for (alias, account) in map {
+                        let line = line_of(&source.content, alias, from, None).unwrap_or(from);
+                        accounts.push((alias.clone(), account, line));
+                    }
+                }
+            }
+            None => {
+                for (alias, account) in root {
+                    let line = line_of(&source.content, alias, 1, None).unwrap_or(1);
+                    accounts.push((alias.clone(), account, line));
+                }
+            }
+        }
+
+        // an account's keys are looked for up to the line of the next account
+        let starts: Vec<usize> = accounts.iter().map(|(_, _, line)| *line).collect();
+        for (alias, account, line) in accounts {
+            let until = starts.iter().copied().filter(|&start| start > line).min();
+            let mut findings = vec![];
+            checked += 1;
+            if let Some(id) = check_account(&alias, account, now, &mut findings) {
+                ids.entry(id)
+                    .or_default()
+                    .push((alias.clone(), source.file.clone(), Some(line)));
+            }
+            for (level, key, message) in findings {
+                diagnostics.push(Diagnostic {
+                    file: source.file.clone(),
+                    line: key
+                        .and_then(|key| line_of(&source.content, &key, line, until))
+                        .or(Some(line)),
+                    level,
+                    account: Some(alias.clone()),
+                    message,
+                });
+            }
+        }
+    }
+
+    for (id, aliases) in ids.into_iter().filter(|(_, aliases)| aliases.len() > 1) {
+        let names: Vec<&str> = aliases.iter().map(|(alias, _, _)| alias.as_str()).collect();
+        for (alias, file, line) in &aliases {
+            diagnostics.push(Diagnostic {
+                file: file.clone(),
+                line: *line,
+                level: Level::Error,
+                account: Some(alias.clone()),
+                message: format!(
+                    "Telegram user {} is used by several accounts: {}",
+                    id,
+                    names.join(", ")
+                ),
+            });
+        }
+    }
+
+    diagnostics.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.level == Level::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    println!(
+        "{} account(s) checked, {} error(s), {} warning(s)",
+        checked, errors, warnings
+    );
+    errors == 0
+}
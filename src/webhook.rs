@@ -0,0 +1,65 @@
+use crate::notify::{self, Kind, Notification};
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::{interval, Instant};
+
+// oldest notifications are dropped once this many are waiting
+const MAX_PENDING: usize = 100;
+
+pub struct Webhook {
+    pub url: String,
+    /// kinds to deliver, all of them when empty
+    pub events: Vec<Kind>,
+    /// notifications are collected for this long and posted together
+    pub batch: Duration,
+    /// minimum time between two posts
+    pub min_interval: Duration,
+}
+
+async fn post(client: &reqwest::Client, url: &str, batch: &[Notification]) {
+    let body = json!({ "notifications": batch });
+    match client.post(url).json(&body).send().await {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => log::error!("webhook {} status: {}", url, response.status()),
+        Err(err) => log::error!("webhook {} err: {:?}", url, err),
+    }
+}
+
+async fn run(webhook: Webhook, mut rx: UnboundedReceiver<Notification>) {
+    let client = reqwest::Client::new();
+    let mut pending: Vec<Notification> = Vec::new();
+    let mut last_post: Option<Instant> = None;
+    let mut tick = interval(webhook.batch.max(Duration::from_secs(1)));
+
+    loop {
+        tokio::select! {
+            notification = rx.recv() => match notification {
+                Some(n) if webhook.events.is_empty() || webhook.events.contains(&n.kind) => {
+                    pending.push(n);
+                    if pending.len() > MAX_PENDING {
+                        pending.remove(0);
+                    }
+                }
+                Some(_) => {}
+                None => break,
+            },
+            _ = tick.tick() => {
+                if pending.is_empty()
+                    || last_post.is_some_and(|t| t.elapsed() < webhook.min_interval)
+                {
+                    continue;
+                }
+                post(&client, &webhook.url, &pending).await;
+                pending.clear();
+                last_post = Some(Instant::now());
+            }
+        }
+    }
+}
+
+/// Deliver notifications to `webhook` in the background.
+pub fn spawn(webhook: Webhook) {
+    let rx = notify::subscribe();
+    tokio::spawn(run(webhook, rx));
+}